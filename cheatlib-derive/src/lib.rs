@@ -0,0 +1,414 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, LitInt, Result};
+
+/// Derives `cheatlib::RemoteStruct` for a struct whose fields are placed with `#[offset(0x48)]`.
+///
+/// Generates a `<Name>View` type that reads single fields lazily, plus a bulk read of the whole struct.
+/// Offsets are the same for both pointer widths, so overlapping fields are rejected at compile time for 4 and 8
+/// byte pointers alike. Layouts that only exist in 32-bit targets use `u32` fields instead of `Pointer`.
+///
+/// The size is rounded up to the largest field alignment like `sizeof` in C, so arrays and vectors of the struct
+/// decode with the right stride. `#[remote(size = 0x40)]` sets the real size for the declared pointer width, a
+/// 64-bit target unless the struct is marked `#[remote(pointer_width = 4)]`. It must cover every field.
+///
+/// ```
+/// use cheatlib::{Pointer, RemoteField, RemoteStruct};
+///
+/// #[derive(RemoteStruct)]
+/// #[remote(size = 0x20)]
+/// struct Player {
+///     #[offset(0x0)]
+///     health: u32,
+///     #[offset(0x8)]
+///     target: Pointer<Player>,
+/// }
+///
+/// assert_eq!(Player::SIZE_64, 0x20);
+/// assert_eq!(Player::SIZE_32, 0xC);
+/// ```
+///
+/// ```compile_fail
+/// use cheatlib::RemoteStruct;
+///
+/// #[derive(RemoteStruct)]
+/// struct Overlapping {
+///     #[offset(0x0)]
+///     position: [f32; 3],
+///     #[offset(0x8)]
+///     health: u32,
+/// }
+/// ```
+///
+/// A pointer only fits 4 bytes on 32-bit targets, so this overlaps as well:
+///
+/// ```compile_fail
+/// use cheatlib::{Pointer, RemoteStruct};
+///
+/// #[derive(RemoteStruct)]
+/// #[remote(pointer_width = 4)]
+/// struct Node {
+///     #[offset(0x0)]
+///     next: Pointer<Node>,
+///     #[offset(0x4)]
+///     value: u32,
+/// }
+/// ```
+#[proc_macro_derive(RemoteStruct, attributes(offset, remote))]
+pub fn derive_remote_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Field {
+    name: syn::Ident,
+    ty: syn::Type,
+    offset: usize,
+    span: Span,
+}
+
+/// Options of the `#[remote(...)]` attribute.
+struct Options {
+    pointer_width: usize,
+    size: Option<usize>,
+}
+
+fn parse_options(input: &DeriveInput) -> Result<Options> {
+    let mut options = Options {
+        pointer_width: 8,
+        size: None,
+    };
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("remote"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("pointer_width") {
+                let value: LitInt = meta.value()?.parse()?;
+                options.pointer_width = value.base10_parse()?;
+                if options.pointer_width != 4 && options.pointer_width != 8 {
+                    return Err(Error::new(value.span(), "pointer width must be 4 or 8"));
+                }
+                Ok(())
+            } else if meta.path.is_ident("size") {
+                let value: LitInt = meta.value()?.parse()?;
+                options.size = Some(value.base10_parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `pointer_width` or `size`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+fn parse_fields(input: &DeriveInput) -> Result<Vec<Field>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "RemoteStruct can only be derived for structs",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(Error::new(
+            input.span(),
+            "RemoteStruct requires named fields",
+        ));
+    };
+
+    let mut fields = vec![];
+    for field in named.named.iter() {
+        let mut offset = None;
+        for attribute in field
+            .attrs
+            .iter()
+            .filter(|attribute| attribute.path().is_ident("offset"))
+        {
+            if offset.is_some() {
+                return Err(Error::new(
+                    attribute.span(),
+                    "duplicate #[offset] attribute",
+                ));
+            }
+            offset = Some(attribute.parse_args::<LitInt>()?.base10_parse::<usize>()?);
+        }
+        let Some(offset) = offset else {
+            return Err(Error::new(field.span(), "missing #[offset(...)] attribute"));
+        };
+        fields.push(Field {
+            name: field.ident.clone().unwrap(),
+            ty: field.ty.clone(),
+            offset,
+            span: field.span(),
+        });
+    }
+    fields.sort_by_key(|field| field.offset);
+
+    for pair in fields.windows(2) {
+        if pair[0].offset == pair[1].offset {
+            return Err(Error::new(
+                pair[1].span,
+                format!(
+                    "field `{}` has the same offset {:#X} as field `{}`",
+                    pair[1].name, pair[1].offset, pair[0].name
+                ),
+            ));
+        }
+    }
+    Ok(fields)
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "RemoteStruct does not support generic structs",
+        ));
+    }
+    let options = parse_options(&input)?;
+    let fields = parse_fields(&input)?;
+
+    let name = &input.ident;
+    let visibility = &input.vis;
+    let view = format_ident!("{}View", name);
+    let size_constant = if options.pointer_width == 4 {
+        quote!(SIZE_32)
+    } else {
+        quote!(SIZE_64)
+    };
+
+    let align = |constant: proc_macro2::TokenStream| {
+        fields.iter().fold(quote!(1usize), |align, field| {
+            let ty = &field.ty;
+            quote! {
+                ::cheatlib::remote_struct::const_max(
+                    #align,
+                    <#ty as ::cheatlib::RemoteField>::#constant,
+                )
+            }
+        })
+    };
+    let align_32 = align(quote!(ALIGN_32));
+    let align_64 = align(quote!(ALIGN_64));
+
+    // end of the last field, without tail padding
+    let extent = |constant: &proc_macro2::TokenStream| {
+        fields.iter().fold(quote!(0usize), |size, field| {
+            let (ty, offset) = (&field.ty, field.offset);
+            quote! {
+                ::cheatlib::remote_struct::const_max(
+                    #size,
+                    #offset + <#ty as ::cheatlib::RemoteField>::#constant,
+                )
+            }
+        })
+    };
+    // an explicit size only applies to the declared pointer width
+    let size = |pointer_width: usize,
+                constant: proc_macro2::TokenStream,
+                align: &proc_macro2::TokenStream| {
+        let extent = extent(&constant);
+        match options.size {
+            Some(size) if pointer_width == options.pointer_width => quote!(#size),
+            _ => quote!((#extent).next_multiple_of(#align)),
+        }
+    };
+    let size_32 = size(4, quote!(SIZE_32), &align_32);
+    let size_64 = size(8, quote!(SIZE_64), &align_64);
+
+    let size_check = options.size.map(|size| {
+        let extent = extent(&size_constant);
+        let message =
+            format!("declared size {size:#X} of `{name}` does not cover all of its fields");
+        quote! {
+            const _: () = assert!(#extent <= #size, #message);
+        }
+    });
+
+    let overlap_checks = fields.windows(2).flat_map(|pair| {
+        let (ty, offset, next_offset) = (&pair[0].ty, pair[0].offset, pair[1].offset);
+        [(4, quote!(SIZE_32)), (8, quote!(SIZE_64))].map(|(pointer_width, constant)| {
+            let message = format!(
+                "field `{}` at {:#X} overlaps field `{}` at {:#X} with {pointer_width} byte pointers",
+                pair[0].name, offset, pair[1].name, next_offset
+            );
+            quote_spanned! {pair[1].span=>
+                const _: () = assert!(
+                    #offset + <#ty as ::cheatlib::RemoteField>::#constant <= #next_offset,
+                    #message
+                );
+            }
+        })
+    });
+
+    let decoders = fields.iter().map(|field| {
+        let (field_name, ty, offset) = (&field.name, &field.ty, field.offset);
+        quote! {
+            #field_name: <#ty as ::cheatlib::RemoteField>::decode(&bytes[#offset..], pointer_width)
+        }
+    });
+
+    let accessors = fields.iter().map(|field| {
+        let (field_name, ty, offset) = (&field.name, &field.ty, field.offset);
+        let pointer_name = format_ident!("{}_ptr", field_name);
+        let read_doc = format!("Reads only `{field_name}`, located at offset {offset:#X}.");
+        let pointer_doc =
+            format!("Typed pointer to `{field_name}`, located at offset {offset:#X}.");
+        quote! {
+            #[doc = #read_doc]
+            pub fn #field_name(&self) -> ::cheatlib::Result<#ty> {
+                <#ty as ::cheatlib::RemoteField>::read_remote(self.memory, self.address + #offset)
+            }
+
+            #[doc = #pointer_doc]
+            pub fn #pointer_name(&self) -> ::cheatlib::RemotePtr<'a, #ty, M> {
+                ::cheatlib::RemotePtr::new(self.memory, self.address + #offset)
+            }
+        }
+    });
+
+    let view_doc = format!("Remote view of a [`{name}`], generated by `#[derive(RemoteStruct)]`.");
+
+    Ok(quote! {
+        #(#overlap_checks)*
+        #size_check
+
+        impl ::cheatlib::RemoteField for #name {
+            const SIZE_32: usize = #size_32;
+            const SIZE_64: usize = #size_64;
+            const ALIGN_32: usize = #align_32;
+            const ALIGN_64: usize = #align_64;
+
+            #[allow(unused_variables)]
+            fn decode(bytes: &[u8], pointer_width: usize) -> Self {
+                Self {
+                    #(#decoders,)*
+                }
+            }
+        }
+
+        #[doc = #view_doc]
+        #visibility struct #view<'a, M> {
+            pub address: usize,
+            pub memory: &'a M,
+        }
+
+        impl<M> ::core::clone::Clone for #view<'_, M> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<M> ::core::marker::Copy for #view<'_, M> {}
+
+        impl<'a, M: ::cheatlib::MemoryAccess> #view<'a, M> {
+            pub fn new(memory: &'a M, address: usize) -> Self {
+                Self { address, memory }
+            }
+
+            /// Reads the whole struct with a single read.
+            pub fn read(&self) -> ::cheatlib::Result<#name> {
+                <#name as ::cheatlib::RemoteField>::read_remote(self.memory, self.address)
+            }
+
+            #(#accessors)*
+        }
+
+        impl ::cheatlib::RemoteStruct for #name {
+            type View<'a, M: ::cheatlib::MemoryAccess + 'a> = #view<'a, M>;
+
+            fn view<M: ::cheatlib::MemoryAccess>(memory: &M, address: usize) -> #view<'_, M> {
+                #view::new(memory, address)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cheatlib::{MemoryAccess, MockMemory, Pointer, Protection, RemoteField, RemoteStruct};
+
+    #[derive(RemoteStruct, Debug, PartialEq)]
+    struct Padded {
+        #[offset(0x0)]
+        id: u64,
+        #[offset(0x8)]
+        flags: u8,
+    }
+
+    #[allow(dead_code)]
+    #[derive(RemoteStruct)]
+    #[remote(pointer_width = 4)]
+    struct Node {
+        #[offset(0x0)]
+        next: Pointer<Node>,
+        #[offset(0x8)]
+        value: u16,
+    }
+
+    #[allow(dead_code)]
+    #[derive(RemoteStruct)]
+    #[remote(size = 0x40)]
+    struct Declared {
+        #[offset(0x10)]
+        value: u32,
+    }
+
+    #[test]
+    fn size_includes_tail_padding() {
+        assert_eq!(Padded::ALIGN_64, 8);
+        assert_eq!(Padded::SIZE_64, 0x10);
+        assert_eq!(Padded::SIZE_32, 0x10);
+        assert_eq!(<[Padded; 3]>::SIZE_64, 0x30);
+    }
+
+    #[test]
+    fn size_depends_on_pointer_width() {
+        assert_eq!(Node::SIZE_32, 0xC);
+        assert_eq!(Node::SIZE_64, 0x10);
+        assert_eq!(Node::ALIGN_32, 4);
+        assert_eq!(Node::ALIGN_64, 8);
+    }
+
+    #[test]
+    fn explicit_size_applies_to_the_declared_width() {
+        assert_eq!(Declared::SIZE_64, 0x40);
+        assert_eq!(Declared::SIZE_32, 0x14);
+    }
+
+    #[test]
+    fn array_elements_use_the_padded_stride() {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![0u8; 0x1000], Protection::READ_WRITE)
+            .unwrap();
+        for index in 0..3u64 {
+            memory
+                .write(0x1000 + index as usize * 0x10, index + 1)
+                .unwrap();
+            memory
+                .write(0x1008 + index as usize * 0x10, index as u8 * 2)
+                .unwrap();
+        }
+
+        let values = <[Padded; 3]>::read_remote(&memory, 0x1000).unwrap();
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(
+                *value,
+                Padded {
+                    id: index as u64 + 1,
+                    flags: index as u8 * 2
+                }
+            );
+        }
+        assert_eq!(
+            Padded::view(&memory, 0x1010).flags().unwrap(),
+            2,
+            "views read single fields"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use std::fmt;
+
+use crate::{patch::write_with_protection, *};
+
+/// Size of the chunks a [`RemoteArena`] allocates, the allocation granularity on windows.
+const DEFAULT_CHUNK_SIZE: usize = 0x10000;
+
+/// Alignment of byte blocks and strings handed out by a [`RemoteArena`].
+const DEFAULT_ALIGNMENT: usize = 16;
+
+/// Memory allocated in the target through [`MemoryAccess::allocate`], freed when dropped.
+///
+/// Offsets passed to the read and write helpers are relative to the start of the allocation and checked against
+/// its size. Writes succeed even if the allocation is not writable, so code can be written into executable memory.
+pub struct RemoteAllocation<M: MemoryAccess> {
+    memory: M,
+    address: usize,
+    size: usize,
+    protection: Protection,
+}
+
+impl<M: MemoryAccess> RemoteAllocation<M> {
+    /// Allocates `size` bytes anywhere in the target.
+    pub fn new(memory: M, size: usize, protection: Protection) -> Result<Self> {
+        Self::allocate(memory, size, protection, None)
+    }
+
+    /// Allocates `size` bytes at `address`, fails if that range is not free.
+    pub fn at(memory: M, address: usize, size: usize, protection: Protection) -> Result<Self> {
+        Self::allocate(memory, size, protection, Some(address))
+    }
+
+    fn allocate(
+        memory: M,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<Self> {
+        if size == 0 {
+            return Err(anyhow!("cannot allocate 0 bytes"));
+        }
+        let address = memory.allocate(size, protection, preferred)?;
+        Ok(Self {
+            memory,
+            address,
+            size,
+            protection,
+        })
+    }
+
+    #[inline]
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    #[inline]
+    pub fn protection(&self) -> Protection {
+        self.protection
+    }
+
+    #[inline]
+    pub fn memory(&self) -> &M {
+        &self.memory
+    }
+
+    fn address_of(&self, offset: usize, len: usize) -> Result<usize> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size => Ok(self.address + offset),
+            _ => Err(anyhow!(
+                "{len} bytes at offset {:#0x} are outside of the {} byte allocation at {:#0x}",
+                offset,
+                self.size,
+                self.address
+            )),
+        }
+    }
+
+    pub fn read_bytes(&self, offset: usize, buffer: &mut [u8]) -> Result<()> {
+        let address = self.address_of(offset, buffer.len())?;
+        self.memory.read_bytes(address, buffer)
+    }
+
+    pub fn write_bytes(&self, offset: usize, data: &[u8]) -> Result<()> {
+        let address = self.address_of(offset, data.len())?;
+        write_with_protection(&self.memory, address, data)
+    }
+
+    pub fn read<T: Pod>(&self, offset: usize) -> Result<T> {
+        let address = self.address_of(offset, mem::size_of::<T>())?;
+        self.memory.read(address)
+    }
+
+    pub fn write<T: Pod>(&self, offset: usize, value: T) -> Result<()> {
+        let data = unsafe {
+            std::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>())
+        };
+        self.write_bytes(offset, data)
+    }
+
+    /// Changes the protection of the whole allocation, for example to make written code executable.
+    pub fn protect(&mut self, protection: Protection) -> Result<()> {
+        self.memory.protect(self.address, self.size, protection)?;
+        self.protection = protection;
+        Ok(())
+    }
+
+    /// Consumes the allocation without freeing it and returns its address.
+    pub fn leak(self) -> usize {
+        let address = self.address;
+        mem::forget(self);
+        address
+    }
+}
+
+impl<M: MemoryAccess> fmt::Debug for RemoteAllocation<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteAllocation")
+            .field("address", &format_args!("{:#X}", self.address))
+            .field("size", &self.size)
+            .field("protection", &self.protection)
+            .finish()
+    }
+}
+
+impl<M: MemoryAccess> Drop for RemoteAllocation<M> {
+    fn drop(&mut self) {
+        let _ = self.memory.free(self.address, self.size);
+    }
+}
+
+/// Bump allocator for many small objects in the target, like strings and argument blocks.
+///
+/// Memory is allocated in chunks and only freed when the arena is dropped, [`RemoteArena::reset`] reuses it.
+pub struct RemoteArena<M: MemoryAccess + Clone> {
+    memory: M,
+    protection: Protection,
+    chunk_size: usize,
+    chunks: Vec<RemoteAllocation<M>>,
+    /// Chunk that is currently handed out from and how much of it is used.
+    current: usize,
+    used: usize,
+}
+
+impl<M: MemoryAccess + Clone> RemoteArena<M> {
+    pub fn new(memory: M, protection: Protection) -> Self {
+        Self {
+            memory,
+            protection,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            chunks: vec![],
+            current: 0,
+            used: 0,
+        }
+    }
+
+    /// Changes the size of chunks allocated from now on, larger objects get a chunk of their own size.
+    pub fn with_chunk_size(self, chunk_size: usize) -> Self {
+        Self { chunk_size, ..self }
+    }
+
+    /// Number of chunks allocated in the target.
+    #[inline]
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Reserves `size` bytes aligned to `alignment`, a power of two up to a page, and returns their address.
+    pub fn alloc(&mut self, size: usize, alignment: usize) -> Result<usize> {
+        if !alignment.is_power_of_two() || alignment > PAGE_SIZE {
+            return Err(anyhow!(
+                "alignment {alignment} is not a power of two up to {PAGE_SIZE:#0x}"
+            ));
+        }
+        let chunk_size = size
+            .max(self.chunk_size)
+            .checked_next_multiple_of(PAGE_SIZE)
+            .ok_or_else(|| anyhow!("cannot allocate {size} bytes in an arena"))?;
+
+        while let Some(chunk) = self.chunks.get(self.current) {
+            // chunks are page aligned, so aligning the offset aligns the address
+            let offset = self.used.next_multiple_of(alignment);
+            if offset
+                .checked_add(size)
+                .is_some_and(|end| end <= chunk.len())
+            {
+                self.used = offset + size;
+                return Ok(chunk.address() + offset);
+            }
+            self.current += 1;
+            self.used = 0;
+        }
+
+        let chunk = RemoteAllocation::new(self.memory.clone(), chunk_size, self.protection)?;
+        let address = chunk.address();
+        self.chunks.push(chunk);
+        self.current = self.chunks.len() - 1;
+        self.used = size;
+        Ok(address)
+    }
+
+    /// Copies `data` into the arena and returns its address.
+    pub fn alloc_bytes(&mut self, data: &[u8]) -> Result<usize> {
+        let address = self.alloc(data.len(), DEFAULT_ALIGNMENT)?;
+        write_with_protection(&self.memory, address, data)?;
+        Ok(address)
+    }
+
+    /// Copies `value` into the arena, aligned for `T`, and returns its address.
+    pub fn alloc_value<T: Pod>(&mut self, value: T) -> Result<usize> {
+        let address = self.alloc(mem::size_of::<T>(), mem::align_of::<T>())?;
+        let data = unsafe {
+            std::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>())
+        };
+        write_with_protection(&self.memory, address, data)?;
+        Ok(address)
+    }
+
+    /// Copies `value` into the arena as a NUL terminated string.
+    pub fn alloc_c_string(&mut self, value: &str) -> Result<usize> {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        self.alloc_bytes(&data)
+    }
+
+    /// Copies `value` into the arena as a NUL terminated UTF-16 string.
+    pub fn alloc_wide_string(&mut self, value: &str) -> Result<usize> {
+        let data = value
+            .encode_utf16()
+            .chain([0])
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        self.alloc_bytes(&data)
+    }
+
+    /// Forgets every object handed out so far and reuses the chunks, nothing is freed or cleared.
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.used = 0;
+    }
+}
+
+impl<M: MemoryAccess + Clone> Process<M> {
+    /// Allocates `size` bytes anywhere in the process.
+    pub fn allocation(&self, size: usize, protection: Protection) -> Result<RemoteAllocation<M>> {
+        RemoteAllocation::new(self.memory.clone(), size, protection)
+    }
+
+    /// Allocates `size` bytes at `address`, fails if that range is not free.
+    pub fn allocation_at(
+        &self,
+        address: usize,
+        size: usize,
+        protection: Protection,
+    ) -> Result<RemoteAllocation<M>> {
+        RemoteAllocation::at(self.memory.clone(), address, size, protection)
+    }
+
+    /// Arena for small objects in the process.
+    pub fn arena(&self, protection: Protection) -> RemoteArena<M> {
+        RemoteArena::new(self.memory.clone(), protection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arena(memory: &MockMemory) -> RemoteArena<MockMemory> {
+        RemoteArena::new(memory.clone(), Protection::READ_WRITE).with_chunk_size(0x2000)
+    }
+
+    #[test]
+    fn arena_fills_chunks_in_order() {
+        let memory = MockMemory::new();
+        let mut arena = arena(&memory);
+        let first = arena.alloc(0x10, 1).unwrap();
+        assert_eq!(first % PAGE_SIZE, 0);
+        assert_eq!(arena.alloc(0x1, 1).unwrap(), first + 0x10);
+        assert_eq!(arena.alloc(0x4, 8).unwrap(), first + 0x18);
+        assert_eq!(arena.chunk_count(), 1);
+
+        let second = arena.alloc(0x1FF0, 1).unwrap();
+        assert_eq!(
+            arena.chunk_count(),
+            2,
+            "the rest of the first chunk is too small"
+        );
+        assert_eq!(memory.query_region(second).unwrap().size, 0x2000);
+
+        let large = arena.alloc(0x3001, 1).unwrap();
+        assert_eq!(arena.chunk_count(), 3);
+        assert_eq!(memory.query_region(large).unwrap().size, 0x4000);
+
+        arena.reset();
+        assert_eq!(arena.alloc(0x10, 1).unwrap(), first, "chunks are reused");
+        assert_eq!(arena.chunk_count(), 3);
+    }
+
+    #[test]
+    fn arena_aligns_and_copies() {
+        let memory = MockMemory::new();
+        let mut arena = arena(&memory);
+        let text = arena.alloc_c_string("abc").unwrap();
+        let value = arena.alloc_value(0x1122_3344_5566_7788u64).unwrap();
+        let page = arena.alloc(1, PAGE_SIZE).unwrap();
+        assert_eq!(value, text + 8);
+        assert_eq!(page, text + PAGE_SIZE);
+        assert_eq!(memory.read::<[u8; 4]>(text).unwrap(), *b"abc\0");
+        assert_eq!(memory.read::<u64>(value).unwrap(), 0x1122_3344_5566_7788);
+    }
+
+    #[test]
+    fn arena_rejects_bad_requests() {
+        let memory = MockMemory::new();
+        let mut arena = arena(&memory);
+        assert!(arena.alloc(1, 3).is_err());
+        assert!(arena.alloc(1, PAGE_SIZE * 2).is_err());
+        assert!(arena.alloc(usize::MAX, 1).is_err());
+        arena.alloc(1, 1).unwrap();
+        assert!(arena.alloc(usize::MAX - 8, 1).is_err());
+        assert_eq!(arena.chunk_count(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{memory::next_page, *};
+
+/// Collects many small reads and executes them together.
+///
+/// Requests touching the same or adjacent pages are coalesced into one page-aligned read, and all reads are
+/// handed to [`MemoryAccess::read_scatter`] at once. A request in a chunk that failed is retried on its own,
+/// so one bad pointer only fails its own request.
+#[derive(Default)]
+pub struct ReadBatch<'a> {
+    requests: Vec<(usize, &'a mut [u8])>,
+}
+
+/// Outcome of [`ReadBatch::execute`], indexed by the values returned when adding requests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchResult {
+    succeeded: Vec<bool>,
+    /// Number of coalesced reads that were issued.
+    pub chunk_count: usize,
+}
+
+impl BatchResult {
+    #[inline]
+    pub fn is_ok(&self, index: usize) -> bool {
+        self.succeeded.get(index).copied().unwrap_or(false)
+    }
+
+    pub fn all_ok(&self) -> bool {
+        self.succeeded.iter().all(|&succeeded| succeeded)
+    }
+
+    /// Indices of the requests that failed.
+    pub fn failed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.succeeded
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &succeeded)| (!succeeded).then_some(index))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.succeeded.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.succeeded.is_empty()
+    }
+}
+
+struct Chunk {
+    start: usize,
+    end: usize,
+    requests: Vec<usize>,
+}
+
+impl<'a> ReadBatch<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a read of `destination.len()` bytes at `address` and returns its index in the result.
+    pub fn add(&mut self, address: usize, destination: &'a mut [u8]) -> usize {
+        self.requests.push((address, destination));
+        self.requests.len() - 1
+    }
+
+    /// Queues a read of a `T` at `address` into `destination`.
+    pub fn add_value<T: Pod>(&mut self, address: usize, destination: &'a mut T) -> usize {
+        let destination = unsafe {
+            std::slice::from_raw_parts_mut(destination as *mut T as *mut u8, mem::size_of::<T>())
+        };
+        self.add(address, destination)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    fn coalesce(&self, succeeded: &mut [bool]) -> Vec<Chunk> {
+        let mut spans = vec![];
+        for (index, (address, destination)) in self.requests.iter().enumerate() {
+            if destination.is_empty() {
+                succeeded[index] = true;
+                continue;
+            }
+            let Some(last) = address.checked_add(destination.len() - 1) else {
+                continue;
+            };
+            spans.push((address & !(PAGE_SIZE - 1), next_page(last), index));
+        }
+        spans.sort_unstable();
+
+        let mut chunks: Vec<Chunk> = vec![];
+        for (start, end, index) in spans {
+            match chunks.last_mut() {
+                Some(chunk) if start <= chunk.end => {
+                    chunk.end = chunk.end.max(end);
+                    chunk.requests.push(index);
+                }
+                _ => chunks.push(Chunk {
+                    start,
+                    end,
+                    requests: vec![index],
+                }),
+            }
+        }
+        chunks
+    }
+
+    /// Executes every queued read against `memory`, filling the destinations of the requests that succeed.
+    pub fn execute<M: MemoryAccess + ?Sized>(mut self, memory: &M) -> BatchResult {
+        let mut succeeded = vec![false; self.requests.len()];
+        let chunks = self.coalesce(&mut succeeded);
+
+        let mut buffers = chunks
+            .iter()
+            .map(|chunk| vec![0u8; chunk.end - chunk.start])
+            .collect::<Vec<_>>();
+        let mut reads = chunks
+            .iter()
+            .zip(buffers.iter_mut())
+            .map(|(chunk, buffer)| (chunk.start, buffer.as_mut_slice()))
+            .collect::<Vec<_>>();
+        let chunk_results = memory.read_scatter(&mut reads);
+
+        for ((chunk, buffer), chunk_succeeded) in
+            chunks.iter().zip(buffers.iter()).zip(chunk_results)
+        {
+            for &index in chunk.requests.iter() {
+                let (address, destination) = &mut self.requests[index];
+                succeeded[index] = if chunk_succeeded {
+                    let offset = *address - chunk.start;
+                    destination.copy_from_slice(&buffer[offset..offset + destination.len()]);
+                    true
+                } else {
+                    memory.read_bytes(*address, destination).is_ok()
+                };
+            }
+        }
+
+        BatchResult {
+            succeeded,
+            chunk_count: chunks.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts every `u32` from 0x1000 up, with the page at 0x3000 unmapped.
+    fn memory() -> MockMemory {
+        let memory = MockMemory::new();
+        let data = |start: u32| (start..start + 0x400).flat_map(u32::to_le_bytes).collect();
+        memory
+            .add_region(0x1000, data(0x400), Protection::READ)
+            .unwrap();
+        memory
+            .add_region(0x2000, data(0x800), Protection::READ)
+            .unwrap();
+        memory
+            .add_region(0x4000, data(0x1000), Protection::READ)
+            .unwrap();
+        memory
+    }
+
+    #[test]
+    fn coalesces_nearby_requests() {
+        let memory = memory();
+        let (mut first, mut overlapping, mut crossing, mut far) = (0u32, [0u8; 6], 0u64, 0u32);
+        let mut batch = ReadBatch::new();
+        batch.add_value(0x1000, &mut first);
+        batch.add(0x1002, &mut overlapping);
+        batch.add_value(0x1FFC, &mut crossing);
+        let far_index = batch.add_value(0x4010, &mut far);
+        let result = batch.execute(&memory);
+
+        assert!(result.all_ok());
+        assert_eq!(result.len(), 4);
+        assert_eq!(result.chunk_count, 2, "the first three share a chunk");
+        assert_eq!(first, 0x400);
+        assert_eq!(overlapping, [0, 0, 1, 4, 0, 0]);
+        assert_eq!(crossing, 0x800 << 32 | 0x7FF);
+        assert!(result.is_ok(far_index));
+        assert_eq!(far, 0x1004);
+    }
+
+    #[test]
+    fn failed_chunks_fall_back_to_single_reads() {
+        let memory = memory();
+        let (mut before, mut across, mut unmapped, mut after) = (0u32, 0u64, 0u32, 0u32);
+        let mut empty = [0u8; 0];
+        let mut wrapping = [0u8; 2];
+        let mut batch = ReadBatch::new();
+        batch.add_value(0x2FF8, &mut before);
+        batch.add_value(0x2FFC, &mut across);
+        batch.add_value(0x3800, &mut unmapped);
+        batch.add_value(0x4000, &mut after);
+        batch.add(0x3000, &mut empty);
+        batch.add(usize::MAX, &mut wrapping);
+        let result = batch.execute(&memory);
+
+        assert_eq!(result.chunk_count, 1);
+        assert_eq!(result.failed().collect::<Vec<_>>(), [1, 2, 5]);
+        assert!(result.is_ok(4), "empty requests always succeed");
+        assert_eq!((before, after), (0xBFE, 0x1000));
+    }
+}
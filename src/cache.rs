@@ -0,0 +1,346 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Pages served from the cache.
+    pub hits: u64,
+    /// Pages read from the wrapped backend.
+    pub misses: u64,
+}
+
+#[derive(Debug)]
+struct CachedPage {
+    data: Vec<u8>,
+    read_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    pages: HashMap<usize, CachedPage>,
+    stats: CacheStats,
+    tick: u64,
+    /// Bumped by every write and invalidation, a page read before a change is not cached after it.
+    generation: u64,
+    /// Last time expired pages were evicted.
+    evicted_at: Option<Instant>,
+}
+
+impl CacheState {
+    fn clear(&mut self) {
+        self.pages.clear();
+        self.generation += 1;
+    }
+
+    fn remove(&mut self, page: usize) {
+        self.pages.remove(&page);
+        self.generation += 1;
+    }
+}
+
+/// Backend wrapper that caches whole pages read from `M`.
+///
+/// Pages stay cached for `lifetime`, or until [`CachedMemory::invalidate`] or [`CachedMemory::next_tick`] when
+/// there is no lifetime. Writes go straight to `M` and update the cached pages they touch.
+/// Clones share the same cache.
+#[derive(Clone, Debug)]
+pub struct CachedMemory<M> {
+    inner: M,
+    lifetime: Option<Duration>,
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl<M: MemoryAccess> CachedMemory<M> {
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            lifetime: None,
+            state: Default::default(),
+        }
+    }
+
+    /// Cached pages expire after `lifetime`.
+    pub fn with_lifetime(inner: M, lifetime: Duration) -> Self {
+        Self {
+            lifetime: Some(lifetime),
+            ..Self::new(inner)
+        }
+    }
+
+    #[inline]
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// Drops every cached page.
+    pub fn invalidate(&self) {
+        self.state.lock().unwrap().clear();
+    }
+
+    /// Drops the cached pages overlapping `address..address + size`.
+    pub fn invalidate_range(&self, address: usize, size: usize) {
+        let mut state = self.state.lock().unwrap();
+        for page in pages(address, size) {
+            state.remove(page);
+        }
+    }
+
+    /// Starts a new tick, everything read during the previous tick is read again.
+    pub fn next_tick(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.clear();
+        state.tick += 1;
+    }
+
+    /// Number of [`CachedMemory::next_tick`] calls so far.
+    pub fn tick(&self) -> u64 {
+        self.state.lock().unwrap().tick
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().unwrap().stats
+    }
+
+    pub fn reset_stats(&self) {
+        self.state.lock().unwrap().stats = CacheStats::default();
+    }
+
+    fn is_fresh(&self, page: &CachedPage) -> bool {
+        self.lifetime
+            .is_none_or(|lifetime| page.read_at.elapsed() < lifetime)
+    }
+
+    /// Caches a page read from the backend unless the cache changed since `generation`, expired pages are evicted
+    /// at most once per lifetime.
+    fn insert(&self, page: usize, data: Vec<u8>, read_at: Instant, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return;
+        }
+        if let Some(lifetime) = self.lifetime {
+            if state
+                .evicted_at
+                .is_none_or(|evicted_at| evicted_at.elapsed() >= lifetime)
+            {
+                state
+                    .pages
+                    .retain(|_, page| page.read_at.elapsed() < lifetime);
+                state.evicted_at = Some(Instant::now());
+            }
+        }
+        state.pages.insert(page, CachedPage { data, read_at });
+    }
+}
+
+/// End of `address..address + size`, an error if it wraps around.
+fn range_end(address: usize, size: usize) -> Result<usize> {
+    address
+        .checked_add(size)
+        .ok_or_else(|| anyhow!("{size:#0x} bytes at {address:#0x} overflow the address space"))
+}
+
+/// Start addresses of the pages overlapping `address..address + size`.
+fn pages(address: usize, size: usize) -> impl Iterator<Item = usize> {
+    let first = address & !(PAGE_SIZE - 1);
+    let last = address.saturating_add(size.max(1) - 1) & !(PAGE_SIZE - 1);
+    (first..=last).step_by(PAGE_SIZE)
+}
+
+impl<M: MemoryAccess> MemoryAccess for CachedMemory<M> {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        let range_end = range_end(address, buffer.len())?;
+        for page in pages(address, buffer.len()) {
+            if buffer.is_empty() {
+                break;
+            }
+            let start = address.max(page);
+            let end = range_end.min(page.saturating_add(PAGE_SIZE));
+            let destination = &mut buffer[start - address..end - address];
+
+            let generation = {
+                let mut state = self.state.lock().unwrap();
+                if let Some(cached) = state.pages.get(&page).filter(|page| self.is_fresh(page)) {
+                    destination.copy_from_slice(&cached.data[start - page..end - page]);
+                    state.stats.hits += 1;
+                    continue;
+                }
+                state.stats.misses += 1;
+                state.generation
+            };
+
+            // the backend is read without the lock, other threads keep using the cache meanwhile
+            let read_at = Instant::now();
+            let mut data = vec![0u8; PAGE_SIZE];
+            if self.inner.read_bytes(page, &mut data).is_ok() {
+                destination.copy_from_slice(&data[start - page..end - page]);
+                self.insert(page, data, read_at, generation);
+            } else {
+                self.state.lock().unwrap().remove(page);
+                self.inner.read_bytes(start, destination)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        let range_end = range_end(address, data.len())?;
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        if let Err(error) = self.inner.write_bytes(address, data) {
+            for page in pages(address, data.len()) {
+                state.pages.remove(&page);
+            }
+            return Err(error);
+        }
+
+        for page in pages(address, data.len()) {
+            if let Some(cached) = state.pages.get_mut(&page) {
+                let start = address.max(page);
+                let end = range_end.min(page.saturating_add(PAGE_SIZE));
+                if start < end {
+                    cached.data[start - page..end - page]
+                        .copy_from_slice(&data[start - address..end - address]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        self.inner.query_region(address)
+    }
+
+    /// Drops the affected pages, so reads notice when they become inaccessible.
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection> {
+        self.invalidate_range(address, size);
+        self.inner.protect(address, size, protection)
+    }
+
+    fn protect_raw(&self, address: usize, size: usize, protection: Protection) -> Result<u32> {
+        self.invalidate_range(address, size);
+        self.inner.protect_raw(address, size, protection)
+    }
+
+    fn restore_protection(&self, address: usize, size: usize, raw: u32) -> Result<()> {
+        self.invalidate_range(address, size);
+        self.inner.restore_protection(address, size, raw)
+    }
+
+    fn ignores_protection(&self) -> bool {
+        self.inner.ignores_protection()
+    }
+
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        let address = self.inner.allocate(size, protection, preferred)?;
+        self.invalidate_range(address, size);
+        Ok(address)
+    }
+
+    fn free(&self, address: usize, size: usize) -> Result<()> {
+        self.invalidate_range(address, size);
+        self.inner.free(address, size)
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        self.inner.regions()
+    }
+
+    fn pointer_width(&self) -> usize {
+        self.inner.pointer_width()
+    }
+}
+
+impl<M: MemoryAccess + Clone> Process<M> {
+    /// Wraps the memory of the process and its modules in a shared [`CachedMemory`].
+    pub fn with_cache(self, lifetime: Option<Duration>) -> Process<CachedMemory<M>> {
+        let memory = match lifetime {
+            Some(lifetime) => CachedMemory::with_lifetime(self.memory, lifetime),
+            None => CachedMemory::new(self.memory),
+        };
+        Process {
+            id: self.id,
+            modules: self
+                .modules
+                .into_iter()
+                .map(|module| Module {
+                    name: module.name,
+                    handle: module.handle,
+                    size: module.size,
+                    base_address: module.base_address,
+                    memory: memory.clone(),
+                })
+                .collect(),
+            memory,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> MockMemory {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![1u8; 0x3000], Protection::READ_WRITE)
+            .unwrap();
+        memory
+    }
+
+    #[test]
+    fn caches_pages() {
+        let cache = CachedMemory::new(memory());
+        assert_eq!(cache.read::<u32>(0x1FFE).unwrap(), 0x0101_0101);
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+
+        cache.inner().write(0x1000, 2u8).unwrap();
+        assert_eq!(
+            cache.read::<u8>(0x1000).unwrap(),
+            1,
+            "served from the cache"
+        );
+        cache.write(0x1001, 3u8).unwrap();
+        assert_eq!(cache.read::<[u8; 2]>(0x1000).unwrap(), [1, 3]);
+
+        cache.next_tick();
+        assert_eq!(cache.tick(), 1);
+        assert_eq!(cache.read::<[u8; 2]>(0x1000).unwrap(), [2, 3]);
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 3 });
+    }
+
+    #[test]
+    fn expired_pages_are_evicted() {
+        let cache = CachedMemory::with_lifetime(memory(), Duration::from_millis(10));
+        cache.read::<u8>(0x1000).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        cache.read::<u8>(0x2000).unwrap();
+        let pages = cache
+            .state
+            .lock()
+            .unwrap()
+            .pages
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(pages, [0x2000]);
+    }
+
+    #[test]
+    fn rejects_wrapping_ranges() {
+        let cache = CachedMemory::new(memory());
+        let mut buffer = [0u8; 0x10];
+        assert!(cache.read_bytes(usize::MAX - 4, &mut buffer).is_err());
+        assert!(cache.write_bytes(usize::MAX - 4, &buffer).is_err());
+        assert!(cache.read_bytes(0x5000, &mut buffer).is_err());
+    }
+}
@@ -0,0 +1,401 @@
+use crate::*;
+
+/// Containers and strings longer than this are treated as corrupt unless the limit is raised.
+const DEFAULT_MAX_ELEMENTS: usize = 0x10000;
+
+/// Standard library implementation the target was built with, which decides the container layouts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CppAbi {
+    /// Microsoft STL, release builds without iterator debugging.
+    Msvc,
+    /// GNU libstdc++ with the C++11 string ABI.
+    Libstdcxx,
+}
+
+/// Reads C++ standard library containers from target memory.
+///
+/// Element types are decoded through [`RemoteField`], so pointers are sized for the target. Lengths read from a
+/// container header are checked against `max_elements` before anything else is read.
+///
+/// Node based containers assume their values need no more alignment than a pointer, and map values are assumed
+/// to be aligned to the largest power of two dividing their size, capped at the pointer width.
+pub struct CppReader<'a, M> {
+    memory: &'a M,
+    abi: CppAbi,
+    max_elements: usize,
+}
+
+impl<'a, M: MemoryAccess> CppReader<'a, M> {
+    pub fn new(memory: &'a M, abi: CppAbi) -> Self {
+        Self {
+            memory,
+            abi,
+            max_elements: DEFAULT_MAX_ELEMENTS,
+        }
+    }
+
+    /// Changes the largest accepted element count, for strings this counts characters.
+    pub fn with_max_elements(self, max_elements: usize) -> Self {
+        Self {
+            max_elements,
+            ..self
+        }
+    }
+
+    #[inline]
+    fn pointer_width(&self) -> usize {
+        self.memory.pointer_width()
+    }
+
+    fn check_count(&self, count: usize, container: &str, address: usize) -> Result<()> {
+        if count > self.max_elements {
+            return Err(anyhow!(
+                "{container} at {:#0x} claims {count} elements, more than the limit of {}",
+                address,
+                self.max_elements
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads the characters of a `std::basic_string` whose characters are `char_size` bytes wide.
+    pub fn read_string_bytes(&self, address: usize, char_size: usize) -> Result<Vec<u8>> {
+        let pointer_width = self.pointer_width();
+        let (data, length) = match self.abi {
+            CppAbi::Msvc => {
+                // union { char _Buf[16]; char* _Ptr; }, size_t _Mysize, size_t _Myres
+                let length = self.memory.read_pointer(address + 16)?;
+                let capacity = self.memory.read_pointer(address + 16 + pointer_width)?;
+                let data = if capacity < 16 / char_size {
+                    address
+                } else {
+                    self.memory.read_pointer(address)?
+                };
+                (data, length)
+            }
+            CppAbi::Libstdcxx => {
+                // char* _M_p, size_t _M_string_length, then the local buffer
+                let data = self.memory.read_pointer(address)?;
+                let length = self.memory.read_pointer(address + pointer_width)?;
+                (data, length)
+            }
+        };
+        self.check_count(length, "std::string", address)?;
+
+        let mut bytes = vec![0u8; length * char_size];
+        self.memory.read_bytes(data, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads a `std::string` holding UTF-8.
+    pub fn read_string(&self, address: usize) -> Result<String> {
+        let bytes = self.read_string_bytes(address, 1)?;
+        String::from_utf8(bytes)
+            .map_err(|_| anyhow!("std::string at {:#0x} is not valid UTF-8", address))
+    }
+
+    /// Reads a `std::wstring`, UTF-16 for MSVC and UTF-32 for libstdc++.
+    pub fn read_wide_string(&self, address: usize) -> Result<String> {
+        match self.abi {
+            CppAbi::Msvc => {
+                let units = self
+                    .read_string_bytes(address, 2)?
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>();
+                String::from_utf16(&units)
+                    .map_err(|_| anyhow!("std::wstring at {:#0x} is not valid UTF-16", address))
+            }
+            CppAbi::Libstdcxx => self
+                .read_string_bytes(address, 4)?
+                .chunks_exact(4)
+                .map(|unit| char::from_u32(u32::from_le_bytes(unit.try_into().unwrap())))
+                .collect::<Option<String>>()
+                .ok_or_else(|| anyhow!("std::wstring at {:#0x} is not valid UTF-32", address)),
+        }
+    }
+
+    /// Reads the elements of a `std::vector<T>`, both implementations store begin, end and capacity end pointers.
+    pub fn read_vector<T: RemoteField>(&self, address: usize) -> Result<Vec<T>> {
+        let pointer_width = self.pointer_width();
+        let element_size = T::remote_size(pointer_width);
+        let first = self.memory.read_pointer(address)?;
+        let last = self.memory.read_pointer(address + pointer_width)?;
+        if last < first || element_size == 0 || (last - first) % element_size != 0 {
+            return Err(anyhow!(
+                "std::vector at {:#0x} has an invalid range {:#0x}..{:#0x}",
+                address,
+                first,
+                last
+            ));
+        }
+        let count = (last - first) / element_size;
+        self.check_count(count, "std::vector", address)?;
+
+        let mut bytes = vec![0u8; count * element_size];
+        self.memory.read_bytes(first, &mut bytes)?;
+        Ok(bytes
+            .chunks_exact(element_size)
+            .map(|element| T::decode(element, pointer_width))
+            .collect())
+    }
+
+    /// Follows `count` node links starting at `first`, returning the node addresses.
+    ///
+    /// The walk must end at `end`, otherwise the list is considered corrupt.
+    fn walk_nodes(
+        &self,
+        container: &str,
+        address: usize,
+        first: usize,
+        end: usize,
+        count: usize,
+    ) -> Result<Vec<usize>> {
+        self.check_count(count, container, address)?;
+        let mut nodes = Vec::with_capacity(count);
+        let mut node = first;
+        for _ in 0..count {
+            if node == end || node == 0 {
+                return Err(anyhow!(
+                    "{container} at {:#0x} ended after {} of {count} elements",
+                    address,
+                    nodes.len()
+                ));
+            }
+            nodes.push(node);
+            node = self.memory.read_pointer(node)?;
+        }
+        if node != end {
+            return Err(anyhow!(
+                "{container} at {:#0x} has more than {count} elements",
+                address
+            ));
+        }
+        Ok(nodes)
+    }
+
+    /// Reads the elements of a `std::list<T>`.
+    pub fn read_list<T: RemoteField>(&self, address: usize) -> Result<Vec<T>> {
+        let pointer_width = self.pointer_width();
+        let (head, count) = match self.abi {
+            // _Nodeptr _Myhead, size_t _Mysize
+            CppAbi::Msvc => (
+                self.memory.read_pointer(address)?,
+                self.memory.read_pointer(address + pointer_width)?,
+            ),
+            // the sentinel node is stored inline, followed by size_t _M_size
+            CppAbi::Libstdcxx => (
+                address,
+                self.memory.read_pointer(address + 2 * pointer_width)?,
+            ),
+        };
+        let first = self.memory.read_pointer(head)?;
+        let nodes = self.walk_nodes("std::list", address, first, head, count)?;
+
+        // nodes start with the next and previous pointers
+        nodes
+            .into_iter()
+            .map(|node| T::read_remote(self.memory, node + 2 * pointer_width))
+            .collect()
+    }
+
+    /// Reads the key value pairs of a `std::unordered_map<K, V>`, in iteration order.
+    pub fn read_unordered_map<K: RemoteField, V: RemoteField>(
+        &self,
+        address: usize,
+    ) -> Result<Vec<(K, V)>> {
+        let pointer_width = self.pointer_width();
+        let value_size = V::remote_size(pointer_width);
+        let value_alignment = (1 << value_size.trailing_zeros().min(31)).min(pointer_width);
+        let value_offset = K::remote_size(pointer_width).next_multiple_of(value_alignment);
+        let pair_size = value_offset + value_size;
+
+        let (nodes, pair_offset) = match self.abi {
+            CppAbi::Msvc => {
+                // the elements live in a std::list after the float max load factor
+                let list = address + pointer_width;
+                let head = self.memory.read_pointer(list)?;
+                let count = self.memory.read_pointer(list + pointer_width)?;
+                let first = self.memory.read_pointer(head)?;
+                let nodes = self.walk_nodes("std::unordered_map", address, first, head, count)?;
+                (nodes, 2 * pointer_width)
+            }
+            CppAbi::Libstdcxx => {
+                // _M_buckets, _M_bucket_count, _M_before_begin, _M_element_count
+                let first = self.memory.read_pointer(address + 2 * pointer_width)?;
+                let count = self.memory.read_pointer(address + 3 * pointer_width)?;
+                let nodes = self.walk_nodes("std::unordered_map", address, first, 0, count)?;
+                (nodes, pointer_width)
+            }
+        };
+
+        let mut pair = vec![0u8; pair_size];
+        nodes
+            .into_iter()
+            .map(|node| {
+                self.memory.read_bytes(node + pair_offset, &mut pair)?;
+                Ok((
+                    K::decode(&pair, pointer_width),
+                    V::decode(&pair[value_offset..], pointer_width),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Reader for C++ containers in this process laid out according to `abi`.
+    pub fn cpp(&self, abi: CppAbi) -> CppReader<'_, M> {
+        CppReader::new(&self.memory, abi)
+    }
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Reader for C++ containers laid out according to `abi`, addresses passed to it are absolute.
+    pub fn cpp(&self, abi: CppAbi) -> CppReader<'_, M> {
+        CppReader::new(&self.memory, abi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: usize = 0x10000;
+
+    fn memory() -> MockMemory {
+        let memory = MockMemory::new();
+        memory
+            .add_region(BASE, vec![0u8; 0x10000], Protection::READ_WRITE)
+            .unwrap();
+        memory
+    }
+
+    fn write_pointers(memory: &MockMemory, address: usize, pointers: &[usize]) {
+        for (index, &pointer) in pointers.iter().enumerate() {
+            memory.write(address + index * 8, pointer as u64).unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_msvc_strings() {
+        let memory = memory();
+        let reader = CppReader::new(&memory, CppAbi::Msvc);
+
+        // short strings live in the inline buffer
+        memory.write_bytes(BASE, b"short").unwrap();
+        write_pointers(&memory, BASE + 16, &[5, 15]);
+        assert_eq!(reader.read_string(BASE).unwrap(), "short");
+
+        memory
+            .write_bytes(BASE + 0x1000, b"a string on the heap")
+            .unwrap();
+        write_pointers(&memory, BASE + 0x100, &[BASE + 0x1000, 0, 20, 31]);
+        assert_eq!(
+            reader.read_string(BASE + 0x100).unwrap(),
+            "a string on the heap"
+        );
+
+        let wide = "wide"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        memory.write_bytes(BASE + 0x200, &wide).unwrap();
+        write_pointers(&memory, BASE + 0x210, &[4, 7]);
+        assert_eq!(reader.read_wide_string(BASE + 0x200).unwrap(), "wide");
+    }
+
+    #[test]
+    fn reads_libstdcxx_strings() {
+        let memory = memory();
+        let reader = CppReader::new(&memory, CppAbi::Libstdcxx);
+        memory.write_bytes(BASE + 0x10, b"inline").unwrap();
+        write_pointers(&memory, BASE, &[BASE + 0x10, 6]);
+        assert_eq!(reader.read_string(BASE).unwrap(), "inline");
+
+        let wide = "wide"
+            .chars()
+            .flat_map(|c| (c as u32).to_le_bytes())
+            .collect::<Vec<_>>();
+        memory.write_bytes(BASE + 0x1000, &wide).unwrap();
+        write_pointers(&memory, BASE + 0x100, &[BASE + 0x1000, 4]);
+        assert_eq!(reader.read_wide_string(BASE + 0x100).unwrap(), "wide");
+    }
+
+    #[test]
+    fn reads_vectors() {
+        let memory = memory();
+        let reader = CppReader::new(&memory, CppAbi::Msvc);
+        for index in 0..4u16 {
+            memory
+                .write(
+                    BASE + 0x1000 + index as usize * 6,
+                    [index, index * 2, index * 3],
+                )
+                .unwrap();
+        }
+        write_pointers(
+            &memory,
+            BASE,
+            &[BASE + 0x1000, BASE + 0x1000 + 24, BASE + 0x1100],
+        );
+        let elements = reader.read_vector::<[u16; 3]>(BASE).unwrap();
+        assert_eq!(elements, vec![[0, 0, 0], [1, 2, 3], [2, 4, 6], [3, 6, 9]]);
+
+        write_pointers(&memory, BASE, &[BASE + 0x1000, BASE + 0x1000 + 25]);
+        assert!(reader.read_vector::<[u16; 3]>(BASE).is_err());
+        write_pointers(&memory, BASE, &[BASE + 0x1000, BASE + 0x1000 + 24]);
+        assert!(reader
+            .with_max_elements(3)
+            .read_vector::<[u16; 3]>(BASE)
+            .is_err());
+    }
+
+    #[test]
+    fn reads_lists() {
+        let memory = memory();
+        // nodes hold next, previous and the value
+        let head = BASE + 0x1000;
+        let nodes = [BASE + 0x1100, BASE + 0x1200, BASE + 0x1300];
+        write_pointers(&memory, head, &[nodes[0], nodes[2]]);
+        for (index, &node) in nodes.iter().enumerate() {
+            let next = nodes.get(index + 1).copied().unwrap_or(head);
+            write_pointers(&memory, node, &[next, 0, index * 10]);
+        }
+
+        write_pointers(&memory, BASE, &[head, 3]);
+        let reader = CppReader::new(&memory, CppAbi::Msvc);
+        assert_eq!(reader.read_list::<u64>(BASE).unwrap(), vec![0, 10, 20]);
+
+        write_pointers(&memory, BASE, &[head, 4]);
+        assert!(reader.read_list::<u64>(BASE).is_err(), "list ends early");
+        write_pointers(&memory, BASE, &[head, 2]);
+        assert!(reader.read_list::<u64>(BASE).is_err(), "list is longer");
+
+        // libstdc++ keeps the sentinel node inline
+        write_pointers(&memory, head + 16, &[3]);
+        write_pointers(&memory, nodes[2], &[head]);
+        let reader = CppReader::new(&memory, CppAbi::Libstdcxx);
+        assert_eq!(reader.read_list::<u64>(head).unwrap(), vec![0, 10, 20]);
+    }
+
+    #[test]
+    fn reads_libstdcxx_unordered_maps() {
+        let memory = memory();
+        let nodes = [BASE + 0x1100, BASE + 0x1200];
+        // next, then the u32 key padded to the alignment of the u64 value
+        write_pointers(&memory, nodes[0], &[nodes[1]]);
+        memory.write(nodes[0] + 8, 7u32).unwrap();
+        memory.write(nodes[0] + 16, 70u64).unwrap();
+        write_pointers(&memory, nodes[1], &[0]);
+        memory.write(nodes[1] + 8, 8u32).unwrap();
+        memory.write(nodes[1] + 16, 80u64).unwrap();
+        write_pointers(&memory, BASE, &[0, 0, nodes[0], 2]);
+
+        let reader = CppReader::new(&memory, CppAbi::Libstdcxx);
+        assert_eq!(
+            reader.read_unordered_map::<u32, u64>(BASE).unwrap(),
+            vec![(7, 70), (8, 80)]
+        );
+    }
+}
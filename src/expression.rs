@@ -0,0 +1,512 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use crate::*;
+
+/// Forwarded exports are followed at most this many times.
+const MAX_FORWARDS: usize = 8;
+
+/// Cheat Engine style address expression, e.g. `[["engine2.dll"+0x50]+8]+0x10` or `kernel32.dll!CreateFileW`.
+///
+/// Numbers are hexadecimal with an optional `0x` prefix, `#` marks a decimal number.
+/// Bare words that are not numbers are module names, quote names that could be read as a number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expression {
+    Number(usize),
+    Module(String),
+    Symbol {
+        module: String,
+        name: String,
+    },
+    /// Reads a pointer of the target pointer width at the inner address.
+    Dereference(Box<Expression>),
+    Negate(Box<Expression>),
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+}
+
+/// Error produced while parsing an [`Expression`], `span` is the byte range of the offending input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Plus,
+    Minus,
+    Star,
+    Bang,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn is_word_character(character: char) -> bool {
+    character.is_ascii_alphanumeric() || "_.#?@$".contains(character)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut characters = text.char_indices().peekable();
+    while let Some((start, character)) = characters.next() {
+        let kind = match character {
+            _ if character.is_whitespace() => continue,
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '!' => TokenKind::Bang,
+            '[' => TokenKind::OpenBracket,
+            ']' => TokenKind::CloseBracket,
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match characters.next() {
+                        Some((_, '"')) => break,
+                        Some((_, character)) => value.push(character),
+                        None => {
+                            return Err(ParseError {
+                                message: "unterminated quoted name".to_string(),
+                                span: start..text.len(),
+                            })
+                        }
+                    }
+                }
+                TokenKind::Quoted(value)
+            }
+            _ if is_word_character(character) => {
+                let mut value = character.to_string();
+                while let Some(&(_, character)) = characters.peek() {
+                    if !is_word_character(character) {
+                        break;
+                    }
+                    value.push(character);
+                    characters.next();
+                }
+                TokenKind::Word(value)
+            }
+            _ => {
+                return Err(ParseError {
+                    message: format!("unexpected character {character:?}"),
+                    span: start..start + character.len_utf8(),
+                })
+            }
+        };
+        let end = characters.peek().map_or(text.len(), |&(index, _)| index);
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+    Ok(tokens)
+}
+
+/// Parses a number, `None` means the word is not a number and names a module.
+fn parse_number(word: &str) -> Option<Result<usize, String>> {
+    if let Some(decimal) = word.strip_prefix('#') {
+        return Some(
+            decimal
+                .parse()
+                .map_err(|_| format!("invalid decimal number {word}")),
+        );
+    }
+    let hex = word
+        .strip_prefix("0x")
+        .or_else(|| word.strip_prefix("0X"))
+        .unwrap_or(word);
+    if hex.is_empty() || !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(usize::from_str_radix(hex, 16).map_err(|_| format!("number {word} is too large")))
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        let span = match self.tokens.get(self.position) {
+            Some(token) => token.span.clone(),
+            None => self.text.len()..self.text.len(),
+        };
+        ParseError {
+            message: message.to_string(),
+            span,
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, message: &str) -> Result<(), ParseError> {
+        if self.peek() != Some(&kind) {
+            return Err(self.error(message));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn parse_sum(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.parse_product()?;
+        loop {
+            let operator = match self.peek() {
+                Some(TokenKind::Plus) => BinaryOperator::Add,
+                Some(TokenKind::Minus) => BinaryOperator::Subtract,
+                _ => return Ok(expression),
+            };
+            self.position += 1;
+            expression = Expression::Binary {
+                operator,
+                left: Box::new(expression),
+                right: Box::new(self.parse_product()?),
+            };
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.parse_unary()?;
+        while self.peek() == Some(&TokenKind::Star) {
+            self.position += 1;
+            expression = Expression::Binary {
+                operator: BinaryOperator::Multiply,
+                left: Box::new(expression),
+                right: Box::new(self.parse_unary()?),
+            };
+        }
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, ParseError> {
+        if self.peek() == Some(&TokenKind::Minus) {
+            self.position += 1;
+            return Ok(Expression::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        let Some(token) = self.next() else {
+            self.position -= 1;
+            return Err(self.error("expected an address, number or module"));
+        };
+        match token.kind {
+            TokenKind::OpenBracket => {
+                let inner = self.parse_sum()?;
+                self.expect(TokenKind::CloseBracket, "expected ']'")?;
+                Ok(Expression::Dereference(Box::new(inner)))
+            }
+            TokenKind::OpenParen => {
+                let inner = self.parse_sum()?;
+                self.expect(TokenKind::CloseParen, "expected ')'")?;
+                Ok(inner)
+            }
+            TokenKind::Word(ref word) | TokenKind::Quoted(ref word) => {
+                let is_quoted = matches!(token.kind, TokenKind::Quoted(_));
+                if !is_quoted {
+                    if let Some(number) = parse_number(word) {
+                        return number
+                            .map(Expression::Number)
+                            .map_err(|message| ParseError {
+                                message,
+                                span: token.span,
+                            });
+                    }
+                }
+                if self.peek() != Some(&TokenKind::Bang) {
+                    return Ok(Expression::Module(word.clone()));
+                }
+                self.position += 1;
+                match self.next().map(|token| token.kind) {
+                    Some(TokenKind::Word(name) | TokenKind::Quoted(name)) => {
+                        Ok(Expression::Symbol {
+                            module: word.clone(),
+                            name,
+                        })
+                    }
+                    _ => {
+                        self.position -= 1;
+                        Err(self.error("expected a symbol name after '!'"))
+                    }
+                }
+            }
+            _ => Err(ParseError {
+                message: "expected an address, number or module".to_string(),
+                span: token.span,
+            }),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            text,
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let expression = parser.parse_sum()?;
+        if parser.position < parser.tokens.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(expression)
+    }
+
+    /// Evaluates the expression against `memory`, looking up module names in `modules`.
+    ///
+    /// Module names match exactly first and case-insensitively second. Symbols are resolved through the
+    /// PE export directory, forwarded exports are followed into other modules of `modules`.
+    pub fn evaluate_with<M: MemoryAccess>(
+        &self,
+        memory: &M,
+        modules: &[Module<M>],
+    ) -> Result<usize> {
+        match self {
+            Self::Number(value) => Ok(*value),
+            Self::Module(name) => Ok(find_module(modules, name)?.base_address),
+            Self::Symbol { module, name } => resolve_symbol(modules, module, name),
+            Self::Dereference(inner) => {
+                let address = inner.evaluate_with(memory, modules)?;
+                memory.read_pointer(address).map_err(|error| {
+                    anyhow!("failed to dereference {address:#0x} in {self}: {error}")
+                })
+            }
+            Self::Negate(inner) => Ok(inner.evaluate_with(memory, modules)?.wrapping_neg()),
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = left.evaluate_with(memory, modules)?;
+                let right = right.evaluate_with(memory, modules)?;
+                Ok(match operator {
+                    BinaryOperator::Add => left.wrapping_add(right),
+                    BinaryOperator::Subtract => left.wrapping_sub(right),
+                    BinaryOperator::Multiply => left.wrapping_mul(right),
+                })
+            }
+        }
+    }
+}
+
+fn find_module<'a, M>(modules: &'a [Module<M>], name: &str) -> Result<&'a Module<M>> {
+    modules
+        .iter()
+        .find(|module| module.name == name)
+        .or_else(|| {
+            modules
+                .iter()
+                .find(|module| module.name.eq_ignore_ascii_case(name))
+        })
+        .ok_or_else(|| anyhow!("no module with name {name} found"))
+}
+
+fn resolve_symbol<M: MemoryAccess>(
+    modules: &[Module<M>],
+    module: &str,
+    name: &str,
+) -> Result<usize> {
+    let mut module = find_module(modules, module)?;
+    let mut name = name.to_string();
+    for _ in 0..MAX_FORWARDS {
+        match module.get_export(&name)? {
+            PeExport::Address(address) => return Ok(address),
+            PeExport::Forwarded(forwarder) => {
+                let Some((forward_module, forward_name)) = forwarder.split_once('.') else {
+                    return Err(anyhow!("invalid export forwarder {forwarder}"));
+                };
+                module = find_module(modules, &format!("{forward_module}.dll"))?;
+                name = forward_name.to_string();
+            }
+        }
+    }
+    Err(anyhow!(
+        "export {name} is forwarded more than {MAX_FORWARDS} times"
+    ))
+}
+
+impl FromStr for Expression {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, ParseError> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{value:#X}"),
+            Self::Module(name) => write!(f, "\"{name}\""),
+            Self::Symbol { module, name } => write!(f, "\"{module}\"!{name}"),
+            Self::Dereference(inner) => write!(f, "[{inner}]"),
+            Self::Negate(inner) => write!(f, "-({inner})"),
+            Self::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let operator = match operator {
+                    BinaryOperator::Add => '+',
+                    BinaryOperator::Subtract => '-',
+                    BinaryOperator::Multiply => '*',
+                };
+                write!(f, "({left}{operator}{right})")
+            }
+        }
+    }
+}
+
+impl<M: MemoryAccess> Process<M> {
+    pub fn evaluate(&self, expression: &Expression) -> Result<usize> {
+        expression.evaluate_with(&self.memory, &self.modules)
+    }
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Evaluates an expression that may only refer to this module.
+    pub fn evaluate(&self, expression: &Expression) -> Result<usize> {
+        expression.evaluate_with(&self.memory, std::slice::from_ref(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(text: &str) -> ParseError {
+        Expression::parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn parses_numbers_and_modules() {
+        assert_eq!(Expression::parse("0x10").unwrap(), Expression::Number(0x10));
+        assert_eq!(
+            Expression::parse("DEAD").unwrap(),
+            Expression::Number(0xDEAD)
+        );
+        assert_eq!(Expression::parse("#10").unwrap(), Expression::Number(10));
+        assert_eq!(
+            Expression::parse("\"DEAD\"").unwrap(),
+            Expression::Module("DEAD".into())
+        );
+        assert_eq!(
+            Expression::parse("kernel32.dll!CreateFileW").unwrap(),
+            Expression::Symbol {
+                module: "kernel32.dll".into(),
+                name: "CreateFileW".into()
+            }
+        );
+        assert_eq!(
+            Expression::parse("[[\"game.exe\"+0x50]+8]-0x10*2")
+                .unwrap()
+                .to_string(),
+            "([([(\"game.exe\"+0x50)]+0x8)]-(0x10*0x2))"
+        );
+    }
+
+    #[test]
+    fn reports_error_spans() {
+        let error = parse_error("game.exe + $%");
+        assert_eq!(error.span, 12..13);
+        assert_eq!(parse_error("[game.exe+8").span, 11..11);
+        assert_eq!(parse_error("\"game.exe+8").span, 0..11);
+        assert_eq!(parse_error("#12G + 1").span, 0..4);
+        assert_eq!(parse_error("8 8").span, 2..3);
+        assert_eq!(parse_error("game.exe!").span, 9..9);
+    }
+
+    #[test]
+    fn rejects_overflowing_numbers() {
+        let error = parse_error("game.exe+FFFFFFFFFFFFFFFFF");
+        assert_eq!(error.span, 9..26);
+        assert!(error.message.contains("too large"), "{}", error.message);
+        assert!(Expression::parse("0x1FFFFFFFFFFFFFFFF").is_err());
+        assert_eq!(
+            Expression::parse("FFFFFFFFFFFFFFFFFz").unwrap(),
+            Expression::Module("FFFFFFFFFFFFFFFFFz".into())
+        );
+    }
+
+    #[test]
+    fn evaluates_against_memory() {
+        let mut process = MockProcess::new(1);
+        let mut data = vec![0u8; 0x1000];
+        data[0x50..0x58].copy_from_slice(&0x2000u64.to_le_bytes());
+        process
+            .add_module("Game.exe", 0x1000, data, Protection::READ)
+            .unwrap();
+
+        let evaluate = |text: &str| process.evaluate(&Expression::parse(text).unwrap());
+        assert_eq!(evaluate("[game.exe+0x50]+0x10").unwrap(), 0x2010);
+        assert_eq!(evaluate("-1+game.exe").unwrap(), 0xFFF);
+        assert!(evaluate("[[game.exe+0x50]]").is_err());
+        assert!(evaluate("other.dll").is_err());
+    }
+
+    #[test]
+    fn bounds_export_name_table() {
+        let mut process = MockProcess::new(1);
+        let mut image = vec![0u8; 0x1000];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        image[0x40..0x44].copy_from_slice(b"PE\0\0");
+        image[0x58..0x5A].copy_from_slice(&0x20Bu16.to_le_bytes());
+        image[0x90..0x94].copy_from_slice(&0x1000u32.to_le_bytes());
+        // export directory at 0x200 claiming 0x4000_0000 names
+        image[0xC8..0xCC].copy_from_slice(&0x200u32.to_le_bytes());
+        image[0xCC..0xD0].copy_from_slice(&0x28u32.to_le_bytes());
+        image[0x218..0x21C].copy_from_slice(&0x4000_0000u32.to_le_bytes());
+        image[0x220..0x224].copy_from_slice(&0x300u32.to_le_bytes());
+        process
+            .add_module("evil.dll", 0x10000, image, Protection::READ)
+            .unwrap();
+
+        let error = process
+            .evaluate(&Expression::parse("evil.dll!Function").unwrap())
+            .unwrap_err();
+        assert!(error.to_string().contains("exceeds the image"), "{error}");
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::*;
+
+/// Shorter intervals are raised to this, a zero interval would keep the thread spinning.
+pub const MIN_FREEZE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Where a frozen value lives.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FreezeTarget {
+    Address(usize),
+    /// Resolved again before every write, so the value follows the object when it moves.
+    Chain(PointerChain),
+}
+
+impl From<usize> for FreezeTarget {
+    fn from(address: usize) -> Self {
+        Self::Address(address)
+    }
+}
+
+impl From<PointerChain> for FreezeTarget {
+    fn from(chain: PointerChain) -> Self {
+        Self::Chain(chain)
+    }
+}
+
+/// Identifies an entry of a [`Freezer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FreezeHandle(u64);
+
+/// Counters of a single [`Freezer`] entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FreezeStats {
+    pub writes: u64,
+    pub errors: u64,
+    /// Errors since the last successful write.
+    pub consecutive_errors: u64,
+    pub last_error: Option<String>,
+    /// Address the value was last written to.
+    pub last_address: Option<usize>,
+    pub paused: bool,
+}
+
+struct Entry {
+    handle: FreezeHandle,
+    target: FreezeTarget,
+    value: Vec<u8>,
+    interval: Duration,
+    next_write: Instant,
+    stats: FreezeStats,
+}
+
+struct State {
+    entries: Vec<Entry>,
+    next_handle: u64,
+    stop: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Keeps values locked by writing them from a background thread, every entry at its own interval.
+///
+/// The thread works on its own copy of the process, so pointer chains can only start in modules that were known
+/// when the freezer was created. Dropping the freezer stops the thread and waits for it.
+pub struct Freezer {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Freezer {
+    pub fn new<M: MemoryAccess + Clone + Send + 'static>(process: &Process<M>) -> Self {
+        let process = Process {
+            id: process.id,
+            memory: process.memory.clone(),
+            modules: process.modules.clone(),
+        };
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                entries: vec![],
+                next_handle: 0,
+                stop: false,
+            }),
+            wake: Condvar::new(),
+        });
+        let thread = {
+            let shared = shared.clone();
+            std::thread::spawn(move || run(&shared, &process))
+        };
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Freezes `value` at `target`, the first write happens right away. The interval is at least
+    /// [`MIN_FREEZE_INTERVAL`].
+    pub fn add<T: Pod>(
+        &self,
+        target: impl Into<FreezeTarget>,
+        value: T,
+        interval: Duration,
+    ) -> FreezeHandle {
+        self.add_bytes(target, value_bytes(&value), interval)
+    }
+
+    /// Freezes raw bytes at `target`.
+    pub fn add_bytes(
+        &self,
+        target: impl Into<FreezeTarget>,
+        value: &[u8],
+        interval: Duration,
+    ) -> FreezeHandle {
+        let mut state = self.shared.lock();
+        let handle = FreezeHandle(state.next_handle);
+        state.next_handle += 1;
+        state.entries.push(Entry {
+            handle,
+            target: target.into(),
+            value: value.to_vec(),
+            interval: interval.max(MIN_FREEZE_INTERVAL),
+            next_write: Instant::now(),
+            stats: FreezeStats::default(),
+        });
+        drop(state);
+        self.shared.wake.notify_all();
+        handle
+    }
+
+    fn with_entry<R>(
+        &self,
+        handle: FreezeHandle,
+        operation: impl FnOnce(&mut Entry) -> R,
+    ) -> Result<R> {
+        let mut state = self.shared.lock();
+        let entry = state
+            .entries
+            .iter_mut()
+            .find(|entry| entry.handle == handle)
+            .ok_or_else(|| anyhow!("freezer has no entry {:?}", handle))?;
+        let result = operation(entry);
+        drop(state);
+        self.shared.wake.notify_all();
+        Ok(result)
+    }
+
+    /// Stops freezing, returns whether the entry existed. A write already in progress still completes.
+    pub fn remove(&self, handle: FreezeHandle) -> bool {
+        let mut state = self.shared.lock();
+        let count = state.entries.len();
+        state.entries.retain(|entry| entry.handle != handle);
+        count != state.entries.len()
+    }
+
+    /// Removes every entry.
+    pub fn clear(&self) {
+        self.shared.lock().entries.clear();
+    }
+
+    /// Pauses or resumes an entry, a resumed entry is written right away.
+    pub fn set_paused(&self, handle: FreezeHandle, paused: bool) -> Result<()> {
+        self.with_entry(handle, |entry| {
+            entry.stats.paused = paused;
+            entry.next_write = Instant::now();
+        })
+    }
+
+    /// Replaces the frozen value, a value of another size is fine.
+    pub fn set_value<T: Pod>(&self, handle: FreezeHandle, value: T) -> Result<()> {
+        self.with_entry(handle, |entry| {
+            entry.value = value_bytes(&value).to_vec();
+            entry.next_write = Instant::now();
+        })
+    }
+
+    pub fn stats(&self, handle: FreezeHandle) -> Option<FreezeStats> {
+        self.with_entry(handle, |entry| entry.stats.clone()).ok()
+    }
+
+    /// Handles of every entry in the order they were added.
+    pub fn handles(&self) -> Vec<FreezeHandle> {
+        self.shared
+            .lock()
+            .entries
+            .iter()
+            .map(|entry| entry.handle)
+            .collect()
+    }
+}
+
+impl Drop for Freezer {
+    fn drop(&mut self) {
+        self.shared.lock().stop = true;
+        self.shared.wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<M: MemoryAccess + Clone + Send + 'static> Process<M> {
+    /// Starts a [`Freezer`] for this process.
+    pub fn freezer(&self) -> Freezer {
+        Freezer::new(self)
+    }
+}
+
+fn value_bytes<T: Pod>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+fn run<M: MemoryAccess + Clone>(shared: &Shared, process: &Process<M>) {
+    let mut state = shared.lock();
+    while !state.stop {
+        let now = Instant::now();
+        let due = state
+            .entries
+            .iter_mut()
+            .filter(|entry| !entry.stats.paused && entry.next_write <= now)
+            .map(|entry| {
+                entry.next_write = now + entry.interval;
+                (entry.handle, entry.target.clone(), entry.value.clone())
+            })
+            .collect::<Vec<_>>();
+
+        if !due.is_empty() {
+            // writing without the lock keeps slow targets from blocking the owner
+            drop(state);
+            let results = due
+                .into_iter()
+                .map(|(handle, target, value)| (handle, write_entry(process, &target, &value)))
+                .collect::<Vec<_>>();
+            state = shared.lock();
+            for (handle, result) in results {
+                let Some(entry) = state
+                    .entries
+                    .iter_mut()
+                    .find(|entry| entry.handle == handle)
+                else {
+                    continue;
+                };
+                match result {
+                    Ok(address) => {
+                        entry.stats.writes += 1;
+                        entry.stats.consecutive_errors = 0;
+                        entry.stats.last_address = Some(address);
+                    }
+                    Err(error) => {
+                        entry.stats.errors += 1;
+                        entry.stats.consecutive_errors += 1;
+                        entry.stats.last_error = Some(error.to_string());
+                    }
+                }
+            }
+            continue;
+        }
+
+        let timeout = state
+            .entries
+            .iter()
+            .filter(|entry| !entry.stats.paused)
+            .map(|entry| entry.next_write.saturating_duration_since(now))
+            .min();
+        state = match timeout {
+            Some(timeout) => {
+                shared
+                    .wake
+                    .wait_timeout(state, timeout)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0
+            }
+            None => shared
+                .wake
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner),
+        };
+    }
+}
+
+fn write_entry<M: MemoryAccess + Clone>(
+    process: &Process<M>,
+    target: &FreezeTarget,
+    value: &[u8],
+) -> Result<usize> {
+    let address = match target {
+        FreezeTarget::Address(address) => *address,
+        FreezeTarget::Chain(chain) => process.resolve(chain)?,
+    };
+    process.memory.write_bytes(address, value)?;
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_interval_is_clamped() {
+        let mut process = MockProcess::new(1);
+        process
+            .add_region(0x1000, vec![0u8; 0x1000], Protection::READ_WRITE)
+            .unwrap();
+        let freezer = process.freezer();
+        freezer.add(0x1000usize, 7u32, Duration::ZERO);
+        freezer.add(0x1004usize, 7u32, Duration::from_secs(5));
+        let intervals = freezer
+            .shared
+            .lock()
+            .entries
+            .iter()
+            .map(|entry| entry.interval)
+            .collect::<Vec<_>>();
+        assert_eq!(intervals, [MIN_FREEZE_INTERVAL, Duration::from_secs(5)]);
+    }
+}
@@ -1,6 +1,28 @@
 pub use {
-    crate::module::Module,
+    crate::allocation::{RemoteAllocation, RemoteArena},
+    crate::batch::{BatchResult, ReadBatch},
+    crate::cache::{CacheStats, CachedMemory},
+    crate::cpp::{CppAbi, CppReader},
+    crate::expression::{Expression, ParseError},
+    crate::freeze::{FreezeHandle, FreezeStats, FreezeTarget, Freezer},
+    crate::memory::{
+        MemoryAccess, MemoryRegion, PartialRead, Pod, Protection, RegionKind, RegionState,
+        PAGE_SIZE,
+    },
+    crate::mock::{MockMemory, MockProcess},
+    crate::module::{Module, ModuleData},
+    crate::patch::{Patch, PatchModifiedError},
+    crate::pe::{CodeCave, PeExport, PeSection},
+    crate::pointer_chain::{PointerChain, PointerChainError},
+    crate::pointer_scan::{PointerMap, PointerMapModule, PointerScanOptions},
     crate::process::Process,
+    crate::protect::ProtectGuard,
+    crate::registry::{Registry, RegistryEntry, Toggle},
+    crate::remote_ptr::RemotePtr,
+    crate::remote_struct::{Pointer, RemoteField, RemoteStruct},
+    crate::scanner::{FirstScan, FloatCompare, NextScan, ScanMatch, Scanner, Value, ValueType},
+    crate::snapshot::{SnapshotMemory, SnapshotProcess},
+    crate::strings::{LengthPrefix, RemoteString, StringEncoding},
     anyhow::anyhow,
     std::ffi::{c_char, c_void, CStr, CString},
     std::mem,
@@ -11,23 +33,77 @@ pub use {
 pub type Error = anyhow::Error;
 pub type Result<T, E = Error> = anyhow::Result<T, E>;
 
+#[cfg(feature = "derive")]
+pub use cheatlib_derive::RemoteStruct;
+
+#[cfg(all(any(windows, target_os = "linux"), feature = "internal"))]
+pub use crate::memory::LocalMemory;
+
+#[cfg(all(any(windows, target_os = "linux"), feature = "external"))]
+pub use crate::memory::RemoteMemory;
+
 #[cfg(windows)]
 pub use windows::*;
 
+#[cfg(target_os = "linux")]
+pub use linux::*;
+
+pub mod allocation;
+
+pub mod batch;
+
+pub mod cache;
+
+pub mod cpp;
+
+pub mod expression;
+
+pub mod freeze;
+
+pub mod memory;
+
+pub mod mock;
+
+pub mod snapshot;
+
 pub mod module;
 
+pub mod patch;
+
 pub mod patternscan;
 
+pub mod pe;
+
+pub mod pointer_chain;
+
+pub mod pointer_scan;
+
 #[cfg(all(windows, feature = "minhook"))]
 pub mod minhook;
 
 #[cfg(windows)]
 pub mod windows;
 
+#[cfg(target_os = "linux")]
+pub mod linux;
+
+#[cfg(windows)]
 pub mod keyboard;
 
 pub mod process;
 
+pub mod protect;
+
+pub mod registry;
+
+pub mod remote_ptr;
+
+pub mod remote_struct;
+
+pub mod scanner;
+
+pub mod strings;
+
 pub mod utilities;
 
 #[cfg(all(windows, feature = "internal"))]
@@ -46,7 +122,10 @@ pub fn allocate_console() -> bool {
 #[cfg(all(windows, feature = "internal"))]
 pub fn set_console_title(title: &str) -> bool {
     use windows_sys::Win32::System::Console::SetConsoleTitleA;
-    unsafe { SetConsoleTitleA(make_lpcstr(title)) > 0 }
+    let Ok(title) = make_lpcstr(title) else {
+        return false;
+    };
+    unsafe { SetConsoleTitleA(title.as_ptr() as *const u8) > 0 }
 }
 
 #[cfg(all(windows, feature = "internal"))]
@@ -60,6 +139,8 @@ pub fn deallocate_console() -> bool {
 /// # Parameters
 /// - `$main`: The main function to be executed when the DLL is attached.
 ///
+/// When the DLL is unloaded, everything in [`registry::global`] is torn down.
+///
 /// # Usage
 /// ```
 /// dll_main!(my_main_function);
@@ -78,11 +159,16 @@ macro_rules! dll_main {
             call_reason: u32,
             _reserved: *mut c_void,
         ) -> BOOL {
+            const DLL_PROCESS_DETACH: u32 = 0;
             const DLL_PROCESS_ATTACH: u32 = 1;
             if call_reason == DLL_PROCESS_ATTACH {
                 disable_thread_library_calls(dll_module);
                 $main();
             }
+            // a non-null reserved pointer means the process is exiting, nothing to restore then
+            if call_reason == DLL_PROCESS_DETACH && _reserved.is_null() {
+                let _ = cheatlib::registry::teardown();
+            }
             TRUE
         }
     };
@@ -0,0 +1,747 @@
+use std::{fs, io, os::unix::fs::FileExt};
+
+use crate::*;
+
+/// Mirrors the windows handle types so platform independent code compiles unchanged.
+pub type HANDLE = isize;
+pub type HMODULE = isize;
+pub type FARPROC = Option<unsafe extern "C" fn() -> isize>;
+
+/// Location of a shared object loaded into the current process.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// Path the object was loaded from, empty for the main executable.
+    pub path: String,
+    pub base_address: usize,
+    pub size: usize,
+}
+
+/// A single line of `/proc/<pid>/maps`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryMapping {
+    pub start: usize,
+    pub end: usize,
+    pub protection: Protection,
+    pub private: bool,
+    pub offset: usize,
+    pub inode: u64,
+    pub path: Option<String>,
+}
+
+impl MemoryMapping {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_ascii_whitespace();
+        let (start, end) = fields.next()?.split_once('-')?;
+        let permissions = fields.next()?.as_bytes();
+        let offset = fields.next()?;
+        let _device = fields.next()?;
+        let inode = fields.next()?;
+        let path = fields.collect::<Vec<_>>().join(" ");
+
+        let mut protection = Protection::NONE;
+        for (flag, value) in [
+            (b'r', Protection::READ),
+            (b'w', Protection::WRITE),
+            (b'x', Protection::EXECUTE),
+        ] {
+            if permissions.contains(&flag) {
+                protection = protection | value;
+            }
+        }
+
+        Some(Self {
+            start: usize::from_str_radix(start, 16).ok()?,
+            end: usize::from_str_radix(end, 16).ok()?,
+            protection,
+            private: permissions.get(3) == Some(&b'p'),
+            offset: usize::from_str_radix(offset, 16).ok()?,
+            inode: inode.parse().ok()?,
+            path: (!path.is_empty()).then_some(path),
+        })
+    }
+}
+
+pub fn read_process_maps(process_id: u32) -> Result<Vec<MemoryMapping>> {
+    let maps = fs::read_to_string(format!("/proc/{process_id}/maps"))
+        .map_err(|error| anyhow!("failed to read maps of process {process_id}: {error}"))?;
+    Ok(maps.lines().filter_map(MemoryMapping::parse).collect())
+}
+
+fn mappings_to_regions(mappings: Vec<MemoryMapping>) -> Vec<MemoryRegion> {
+    let image_paths = mappings
+        .iter()
+        .filter(|mapping| mapping.protection.is_executable())
+        .filter_map(|mapping| mapping.path.clone())
+        .filter(|path| path.starts_with('/'))
+        .collect::<std::collections::HashSet<_>>();
+
+    mappings
+        .into_iter()
+        .map(|mapping| {
+            let kind = match mapping.path.as_deref() {
+                Some(path) if image_paths.contains(path) => RegionKind::Image,
+                Some(path) if path.starts_with('/') => RegionKind::Mapped,
+                _ if !mapping.private => RegionKind::Mapped,
+                _ => RegionKind::Private,
+            };
+            MemoryRegion {
+                base_address: mapping.start,
+                size: mapping.end - mapping.start,
+                protection: mapping.protection,
+                state: RegionState::Committed,
+                kind,
+                path: mapping.path,
+            }
+        })
+        .collect()
+}
+
+/// Returns every mapped region of the process.
+pub fn query_process_regions(process_id: u32) -> Result<Vec<MemoryRegion>> {
+    Ok(mappings_to_regions(read_process_maps(process_id)?))
+}
+
+/// Returns the mapping containing `address`, or the unmapped gap around it with no access.
+pub fn query_process_region(process_id: u32, address: usize) -> Result<MemoryRegion> {
+    Ok(region_at(&query_process_regions(process_id)?, address))
+}
+
+/// Finds the region containing `address` in `regions`, as returned by [`query_process_regions`].
+pub fn region_at(regions: &[MemoryRegion], address: usize) -> MemoryRegion {
+    let mut gap_start = 0;
+    for region in regions {
+        if address < region.base_address {
+            return MemoryRegion {
+                base_address: gap_start,
+                size: region.base_address - gap_start,
+                state: RegionState::Free,
+                ..Default::default()
+            };
+        }
+        if address < region.end_address() {
+            return region.clone();
+        }
+        gap_start = region.end_address();
+    }
+    MemoryRegion {
+        base_address: gap_start,
+        size: usize::MAX - gap_start,
+        state: RegionState::Free,
+        ..Default::default()
+    }
+}
+
+/// Reads the ELF class of the process executable, 4 for 32-bit and 8 for 64-bit processes.
+pub fn get_process_pointer_width(process_id: u32) -> Option<usize> {
+    let mut header = [0u8; 5];
+    fs::File::open(format!("/proc/{process_id}/exe"))
+        .ok()?
+        .read_exact_at(&mut header, 0)
+        .ok()?;
+    match header {
+        [0x7F, b'E', b'L', b'F', 1] => Some(4),
+        [0x7F, b'E', b'L', b'F', 2] => Some(8),
+        _ => None,
+    }
+}
+
+fn process_matches_name(process_id: u32, name: &str) -> bool {
+    if let Ok(comm) = fs::read_to_string(format!("/proc/{process_id}/comm")) {
+        if comm.trim_end_matches('\n') == name {
+            return true;
+        }
+    }
+
+    let Ok(cmdline) = fs::read(format!("/proc/{process_id}/cmdline")) else {
+        return false;
+    };
+    let Some(executable) = cmdline.split(|&byte| byte == 0).next() else {
+        return false;
+    };
+    // wine and proton report windows style paths
+    String::from_utf8_lossy(executable)
+        .rsplit(['/', '\\'])
+        .next()
+        == Some(name)
+}
+
+pub fn get_process_id_by_name(name: &str) -> Option<u32> {
+    fs::read_dir("/proc")
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .find(|&process_id| process_matches_name(process_id, name))
+}
+
+pub fn get_process_modules<M: Clone>(memory: &M, process_id: u32) -> Vec<Module<M>> {
+    let Ok(mappings) = read_process_maps(process_id) else {
+        return vec![];
+    };
+
+    let mut modules: Vec<(String, Module<M>)> = vec![];
+    for mapping in mappings {
+        let Some(path) = mapping.path.filter(|path| path.starts_with('/')) else {
+            continue;
+        };
+
+        if let Some((_, module)) = modules.iter_mut().find(|(other, _)| *other == path) {
+            let end_address = mapping.end.max(module.base_address + module.size);
+            module.base_address = module.base_address.min(mapping.start);
+            module.size = end_address - module.base_address;
+            continue;
+        }
+
+        let name = file_name(&path).to_string();
+        modules.push((
+            path,
+            Module {
+                name,
+                handle: 0,
+                size: mapping.end - mapping.start,
+                base_address: mapping.start,
+                memory: memory.clone(),
+            },
+        ));
+    }
+
+    modules.into_iter().map(|(_, module)| module).collect()
+}
+
+fn access_process_mem_file(
+    process_id: u32,
+    address: usize,
+    buffer: &mut [u8],
+    write: bool,
+) -> io::Result<()> {
+    let file = fs::OpenOptions::new()
+        .read(!write)
+        .write(write)
+        .open(format!("/proc/{process_id}/mem"))?;
+    if write {
+        file.write_all_at(buffer, address as u64)
+    } else {
+        file.read_exact_at(buffer, address as u64)
+    }
+}
+
+/// Reads through `process_vm_readv`, falling back to `/proc/<pid>/mem` when it is unavailable.
+pub fn read_process_memory<T>(
+    process_id: u32,
+    address: usize,
+    buffer: *mut T,
+    size: usize,
+) -> Result<()> {
+    let local = libc::iovec {
+        iov_base: buffer as *mut c_void,
+        iov_len: size,
+    };
+    let remote = libc::iovec {
+        iov_base: address as *mut c_void,
+        iov_len: size,
+    };
+    let read =
+        unsafe { libc::process_vm_readv(process_id as libc::pid_t, &local, 1, &remote, 1, 0) };
+    if read == size as isize {
+        return Ok(());
+    }
+
+    let error = io::Error::last_os_error();
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer as *mut u8, size) };
+    access_process_mem_file(process_id, address, buffer, false).map_err(|_| {
+        anyhow!(
+            "process_vm_readv failed for target: {:#0x}. Error code: {}. Description: {}",
+            address,
+            error.raw_os_error().unwrap_or_default(),
+            error
+        )
+    })
+}
+
+/// Reads every `(address, buffer)` pair with as few `process_vm_readv` calls as possible.
+///
+/// Returns how many leading reads completed, the read after them failed or was cut short.
+pub fn read_process_memory_vectored(process_id: u32, reads: &mut [(usize, &mut [u8])]) -> usize {
+    const IOV_MAX: usize = 1024;
+
+    let mut completed = 0;
+    while completed < reads.len() {
+        let end = (completed + IOV_MAX).min(reads.len());
+        let reads = &mut reads[completed..end];
+        let local = reads
+            .iter_mut()
+            .map(|(_, buffer)| libc::iovec {
+                iov_base: buffer.as_mut_ptr() as *mut c_void,
+                iov_len: buffer.len(),
+            })
+            .collect::<Vec<_>>();
+        let remote = reads
+            .iter()
+            .map(|(address, buffer)| libc::iovec {
+                iov_base: *address as *mut c_void,
+                iov_len: buffer.len(),
+            })
+            .collect::<Vec<_>>();
+        let read = unsafe {
+            libc::process_vm_readv(
+                process_id as libc::pid_t,
+                local.as_ptr(),
+                local.len() as libc::c_ulong,
+                remote.as_ptr(),
+                remote.len() as libc::c_ulong,
+                0,
+            )
+        };
+        if read < 0 {
+            return completed;
+        }
+
+        let mut remaining = read as usize;
+        for (_, buffer) in reads.iter() {
+            if remaining < buffer.len() {
+                return completed;
+            }
+            remaining -= buffer.len();
+            completed += 1;
+        }
+    }
+    completed
+}
+
+/// Writes through `process_vm_writev`, falling back to `/proc/<pid>/mem` when it is unavailable.
+///
+/// The fallback ignores page protections, just like `WriteProcessMemory`.
+pub fn write_process_memory<T>(
+    process_id: u32,
+    address: usize,
+    buffer: *mut T,
+    size: usize,
+) -> Result<()> {
+    let local = libc::iovec {
+        iov_base: buffer as *mut c_void,
+        iov_len: size,
+    };
+    let remote = libc::iovec {
+        iov_base: address as *mut c_void,
+        iov_len: size,
+    };
+    let written =
+        unsafe { libc::process_vm_writev(process_id as libc::pid_t, &local, 1, &remote, 1, 0) };
+    if written == size as isize {
+        return Ok(());
+    }
+
+    let error = io::Error::last_os_error();
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer as *mut u8, size) };
+    access_process_mem_file(process_id, address, buffer, true).map_err(|_| {
+        anyhow!(
+            "process_vm_writev failed for target: {:#0x}. Error code: {}. Description: {}",
+            address,
+            error.raw_os_error().unwrap_or_default(),
+            error
+        )
+    })
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+unsafe extern "C" fn find_loaded_object(
+    info: *mut libc::dl_phdr_info,
+    _size: usize,
+    data: *mut c_void,
+) -> i32 {
+    let (name, result) = &mut *(data as *mut (&str, Option<ModuleInfo>));
+    let info = &*info;
+
+    let path = if info.dlpi_name.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(info.dlpi_name)
+            .to_string_lossy()
+            .into_owned()
+    };
+    let matches = if path.is_empty() {
+        std::env::current_exe()
+            .ok()
+            .and_then(|executable| Some(executable.file_name()?.to_str()? == *name))
+            .unwrap_or(false)
+    } else {
+        path == *name || file_name(&path) == *name
+    };
+    if !matches {
+        return 0;
+    }
+
+    let headers = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+    let (start, end) = headers
+        .iter()
+        .filter(|header| header.p_type == libc::PT_LOAD)
+        .fold((usize::MAX, 0), |(start, end), header| {
+            let segment_start = header.p_vaddr as usize;
+            let segment_end = (header.p_vaddr + header.p_memsz) as usize;
+            (start.min(segment_start), end.max(segment_end))
+        });
+    if start >= end {
+        return 0;
+    }
+
+    *result = Some(ModuleInfo {
+        path,
+        base_address: info.dlpi_addr as usize + start,
+        size: end - start,
+    });
+    1
+}
+
+/// Finds a loaded shared object by file name or full path using `dl_iterate_phdr`.
+///
+/// The size spans all `PT_LOAD` segments.
+pub fn get_module_info(name: &str) -> Result<ModuleInfo> {
+    let mut search: (&str, Option<ModuleInfo>) = (name, None);
+    unsafe {
+        libc::dl_iterate_phdr(
+            Some(find_loaded_object),
+            &mut search as *mut (&str, Option<ModuleInfo>) as *mut c_void,
+        )
+    };
+    search
+        .1
+        .ok_or_else(|| anyhow!("no shared object named {name} is loaded"))
+}
+
+/// Returns the `dlopen` handle of an already loaded shared object without loading it.
+/// An empty path returns the handle of the main executable.
+///
+/// The reference taken by `dlopen` is released again, the handle stays valid while the object is loaded.
+pub fn get_module_handle(path: &str) -> Result<HMODULE> {
+    let path = make_lpcstr(path)?;
+    let path_pointer = if path.as_bytes().is_empty() {
+        ptr::null()
+    } else {
+        path.as_ptr()
+    };
+
+    let handle = unsafe { libc::dlopen(path_pointer, libc::RTLD_LAZY | libc::RTLD_NOLOAD) };
+    if handle.is_null() {
+        let error = unsafe { libc::dlerror() };
+        let description = if error.is_null() {
+            Default::default()
+        } else {
+            unsafe { CStr::from_ptr(error) }.to_string_lossy()
+        };
+        return Err(anyhow!(
+            "dlopen failed for {:?}. Description: {}",
+            path,
+            description
+        ));
+    }
+    unsafe { libc::dlclose(handle) };
+    Ok(handle as HMODULE)
+}
+
+/// Rounds `address..address + size` out to the system page size, targets on the same machine share it.
+fn page_span(address: usize, size: usize) -> (usize, usize) {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let start = address & !(page_size - 1);
+    let end = (address + size.max(1)).next_multiple_of(page_size);
+    (start, end)
+}
+
+/// Changes the protection of all pages spanning `address..address + size`.
+pub fn mprotect(address: usize, size: usize, prot: i32) -> Result<()> {
+    let (start, end) = page_span(address, size);
+
+    if unsafe { libc::mprotect(start as *mut c_void, end - start, prot) } != 0 {
+        let error = io::Error::last_os_error();
+        return Err(anyhow!(
+            "mprotect failed for target: {:#0x}. Error code: {}. Description: {}",
+            address,
+            error.raw_os_error().unwrap_or_default(),
+            error
+        ));
+    }
+    Ok(())
+}
+
+/// Flags for an anonymous private mapping, fixed at `preferred` without replacing anything there.
+fn mmap_flags(preferred: Option<usize>) -> i32 {
+    let flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS;
+    match preferred {
+        Some(_) => flags | libc::MAP_FIXED_NOREPLACE,
+        None => flags,
+    }
+}
+
+/// Kernels before 4.17 treat `MAP_FIXED_NOREPLACE` as a hint, so the address is checked as well.
+fn check_preferred(address: usize, preferred: Option<usize>) -> Result<()> {
+    match preferred {
+        Some(preferred) if preferred != address => Err(anyhow!(
+            "mmap placed {:#0x} at {:#0x} instead",
+            preferred,
+            address
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Maps `size` bytes of zeroed anonymous memory with `prot`, at `preferred` if given.
+pub fn mmap(size: usize, prot: i32, preferred: Option<usize>) -> Result<usize> {
+    let address = unsafe {
+        libc::mmap(
+            preferred.unwrap_or_default() as *mut c_void,
+            size,
+            prot,
+            mmap_flags(preferred),
+            -1,
+            0,
+        )
+    };
+    if address == libc::MAP_FAILED {
+        let error = io::Error::last_os_error();
+        return Err(anyhow!(
+            "mmap failed for {} bytes. Error code: {}. Description: {}",
+            size,
+            error.raw_os_error().unwrap_or_default(),
+            error
+        ));
+    }
+    if let Err(error) = check_preferred(address as usize, preferred) {
+        let _ = munmap(address as usize, size);
+        return Err(error);
+    }
+    Ok(address as usize)
+}
+
+pub fn munmap(address: usize, size: usize) -> Result<()> {
+    if unsafe { libc::munmap(address as *mut c_void, size) } != 0 {
+        let error = io::Error::last_os_error();
+        return Err(anyhow!(
+            "munmap failed for target: {:#0x}. Error code: {}. Description: {}",
+            address,
+            error.raw_os_error().unwrap_or_default(),
+            error
+        ));
+    }
+    Ok(())
+}
+
+/// Detaches from a process attached with `PTRACE_ATTACH`.
+struct PtraceAttachment {
+    pid: libc::pid_t,
+    /// Signals that arrived while attached, delivered again on detach instead of being swallowed.
+    pending: Vec<i32>,
+}
+
+impl PtraceAttachment {
+    fn attach(process_id: u32) -> Result<Self> {
+        let pid = process_id as libc::pid_t;
+        let null = ptr::null_mut::<c_void>();
+        if unsafe { libc::ptrace(libc::PTRACE_ATTACH, pid, null, null) } != 0 {
+            let error = io::Error::last_os_error();
+            return Err(anyhow!(
+                "failed to attach to process {process_id}. Description: {error}"
+            ));
+        }
+        let mut attachment = Self {
+            pid,
+            pending: vec![],
+        };
+        attachment.wait_for(libc::SIGSTOP, libc::PTRACE_CONT)?;
+        Ok(attachment)
+    }
+
+    /// Waits until the process stops with `signal`.
+    ///
+    /// A stop for any other signal is recorded for the detach and the process is resumed with `request`.
+    fn wait_for(&mut self, signal: i32, request: libc::c_uint) -> Result<()> {
+        let null = ptr::null_mut::<c_void>();
+        loop {
+            let mut status = 0;
+            if unsafe { libc::waitpid(self.pid, &mut status, libc::__WALL) } != self.pid
+                || !libc::WIFSTOPPED(status)
+            {
+                return Err(anyhow!("process {} did not stop after ptrace", self.pid));
+            }
+            let stop_signal = libc::WSTOPSIG(status);
+            if stop_signal == signal {
+                return Ok(());
+            }
+            self.pending.push(stop_signal);
+            if unsafe { libc::ptrace(request, self.pid, null, null) } != 0 {
+                return Err(anyhow!("failed to resume process {}", self.pid));
+            }
+        }
+    }
+}
+
+impl Drop for PtraceAttachment {
+    fn drop(&mut self) {
+        let null = ptr::null_mut::<c_void>();
+        let signal = self.pending.first().copied().unwrap_or_default();
+        unsafe {
+            libc::ptrace(
+                libc::PTRACE_DETACH,
+                self.pid,
+                null,
+                signal as usize as *mut c_void,
+            )
+        };
+        for &signal in self.pending.iter().skip(1) {
+            unsafe { libc::kill(self.pid, signal) };
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn ptrace_registers(
+    pid: libc::pid_t,
+    request: libc::c_uint,
+    registers: &mut libc::user_regs_struct,
+) -> Result<()> {
+    let registers = registers as *mut libc::user_regs_struct as *mut c_void;
+    if unsafe { libc::ptrace(request, pid, ptr::null_mut::<c_void>(), registers) } != 0 {
+        let error = io::Error::last_os_error();
+        return Err(anyhow!(
+            "failed to access the registers of process {pid}. Description: {error}"
+        ));
+    }
+    Ok(())
+}
+
+/// Executes a system call on the main thread of a 64-bit process and returns its result.
+///
+/// Uses a `syscall` instruction found in the vdso of the target, the thread state is restored afterwards. Only the
+/// main thread is stopped while the call runs.
+#[cfg(target_arch = "x86_64")]
+pub fn remote_syscall(process_id: u32, number: i64, args: [usize; 6]) -> Result<usize> {
+    if get_process_pointer_width(process_id) != Some(8) {
+        return Err(anyhow!(
+            "remote system calls are only supported in 64-bit processes"
+        ));
+    }
+
+    let vdso = read_process_maps(process_id)?
+        .into_iter()
+        .find(|mapping| mapping.path.as_deref() == Some("[vdso]"))
+        .ok_or_else(|| anyhow!("process {process_id} has no vdso"))?;
+    let mut code = vec![0u8; vdso.end - vdso.start];
+    read_process_memory(process_id, vdso.start, code.as_mut_ptr(), code.len())?;
+    let instruction = vdso.start
+        + code
+            .windows(2)
+            .position(|bytes| bytes == [0x0F, 0x05])
+            .ok_or_else(|| anyhow!("no syscall instruction in the vdso of process {process_id}"))?;
+
+    let mut attachment = PtraceAttachment::attach(process_id)?;
+    let pid = attachment.pid;
+    let mut saved = unsafe { mem::zeroed::<libc::user_regs_struct>() };
+    ptrace_registers(pid, libc::PTRACE_GETREGS, &mut saved)?;
+
+    let mut registers = saved;
+    registers.rip = instruction as u64;
+    registers.rax = number as u64;
+    // keeps the kernel from restarting an interrupted system call with our registers
+    registers.orig_rax = u64::MAX;
+    registers.rdi = args[0] as u64;
+    registers.rsi = args[1] as u64;
+    registers.rdx = args[2] as u64;
+    registers.r10 = args[3] as u64;
+    registers.r8 = args[4] as u64;
+    registers.r9 = args[5] as u64;
+
+    let result = ptrace_registers(pid, libc::PTRACE_SETREGS, &mut registers)
+        .and_then(|_| {
+            let null = ptr::null_mut::<c_void>();
+            if unsafe { libc::ptrace(libc::PTRACE_SINGLESTEP, pid, null, null) } != 0 {
+                return Err(anyhow!("failed to single step process {process_id}"));
+            }
+            // a stop for another signal means the step has not run yet
+            attachment.wait_for(libc::SIGTRAP, libc::PTRACE_SINGLESTEP)
+        })
+        .and_then(|_| ptrace_registers(pid, libc::PTRACE_GETREGS, &mut registers));
+    ptrace_registers(pid, libc::PTRACE_SETREGS, &mut saved)?;
+    result?;
+
+    let value = registers.rax as i64;
+    if (-4095..0).contains(&value) {
+        let error = io::Error::from_raw_os_error(-value as i32);
+        return Err(anyhow!(
+            "system call {number} failed in process {process_id}. Error code: {}. Description: {}",
+            -value,
+            error
+        ));
+    }
+    Ok(value as usize)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn remote_syscall(_process_id: u32, _number: i64, _args: [usize; 6]) -> Result<usize> {
+    Err(anyhow!("remote system calls are only supported on x86_64"))
+}
+
+/// Changes the protection of all pages spanning `address..address + size` in another process.
+pub fn mprotect_remote(process_id: u32, address: usize, size: usize, prot: i32) -> Result<()> {
+    let (start, end) = page_span(address, size);
+    remote_syscall(
+        process_id,
+        libc::SYS_mprotect,
+        [start, end - start, prot as usize, 0, 0, 0],
+    )
+    .map_err(|error| {
+        anyhow!(
+            "mprotect failed for target: {:#0x} in process {process_id}. {error}",
+            address
+        )
+    })?;
+    Ok(())
+}
+
+/// Maps `size` bytes of zeroed anonymous memory with `prot` in another process, at `preferred` if given.
+pub fn mmap_remote(
+    process_id: u32,
+    size: usize,
+    prot: i32,
+    preferred: Option<usize>,
+) -> Result<usize> {
+    let address = remote_syscall(
+        process_id,
+        libc::SYS_mmap,
+        [
+            preferred.unwrap_or_default(),
+            size,
+            prot as usize,
+            mmap_flags(preferred) as usize,
+            usize::MAX,
+            0,
+        ],
+    )
+    .map_err(|error| anyhow!("mmap failed for {size} bytes in process {process_id}. {error}"))?;
+    if let Err(error) = check_preferred(address, preferred) {
+        let _ = munmap_remote(process_id, address, size);
+        return Err(error);
+    }
+    Ok(address)
+}
+
+pub fn munmap_remote(process_id: u32, address: usize, size: usize) -> Result<()> {
+    remote_syscall(process_id, libc::SYS_munmap, [address, size, 0, 0, 0, 0]).map_err(|error| {
+        anyhow!(
+            "munmap failed for target: {:#0x} in process {process_id}. {error}",
+            address
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_handle_rejects_nul() {
+        assert!(get_module_handle("").is_ok(), "the main executable");
+        let error = get_module_handle("libc.so.6\0").unwrap_err();
+        assert!(error.to_string().contains("NUL"), "{error}");
+    }
+}
@@ -0,0 +1,912 @@
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, Range},
+};
+
+use crate::*;
+
+#[cfg(all(windows, feature = "external"))]
+use std::sync::Arc;
+
+#[cfg(windows)]
+use windows_sys::Win32::System::Memory::{
+    MEM_COMMIT, MEM_IMAGE, MEM_MAPPED, MEM_RESERVE, PAGE_EXECUTE_WRITECOPY, PAGE_GUARD,
+    PAGE_NOACCESS, PAGE_WRITECOPY,
+};
+
+/// Platform independent page protection flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Protection(u8);
+
+impl Protection {
+    pub const NONE: Self = Self(0);
+    pub const READ: Self = Self(1 << 0);
+    pub const WRITE: Self = Self(1 << 1);
+    pub const EXECUTE: Self = Self(1 << 2);
+    pub const READ_WRITE: Self = Self(Self::READ.0 | Self::WRITE.0);
+    pub const READ_EXECUTE: Self = Self(Self::READ.0 | Self::EXECUTE.0);
+    pub const READ_WRITE_EXECUTE: Self = Self(Self::READ.0 | Self::WRITE.0 | Self::EXECUTE.0);
+
+    #[inline]
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits & Self::READ_WRITE_EXECUTE.0)
+    }
+
+    #[inline]
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    pub const fn is_readable(self) -> bool {
+        self.contains(Self::READ)
+    }
+
+    #[inline]
+    pub const fn is_writable(self) -> bool {
+        self.contains(Self::WRITE)
+    }
+
+    #[inline]
+    pub const fn is_executable(self) -> bool {
+        self.contains(Self::EXECUTE)
+    }
+}
+
+impl fmt::Display for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |enabled, character| if enabled { character } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(self.is_readable(), 'r'),
+            flag(self.is_writable(), 'w'),
+            flag(self.is_executable(), 'x')
+        )
+    }
+}
+
+impl BitOr for Protection {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Protection {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+#[cfg(windows)]
+impl Protection {
+    /// Converts `PAGE_*` flags into a [`Protection`]. Guard pages are reported as inaccessible.
+    pub fn from_page_flags(flags: u32) -> Self {
+        if flags & PAGE_GUARD != 0 {
+            return Self::NONE;
+        }
+        match flags & 0xFF {
+            PAGE_READONLY => Self::READ,
+            PAGE_READWRITE | PAGE_WRITECOPY => Self::READ_WRITE,
+            PAGE_EXECUTE => Self::EXECUTE,
+            PAGE_EXECUTE_READ => Self::READ_EXECUTE,
+            PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY => Self::READ_WRITE_EXECUTE,
+            _ => Self::NONE,
+        }
+    }
+
+    /// Converts into the closest matching `PAGE_*` flag. Write access always implies read access.
+    pub fn to_page_flags(self) -> u32 {
+        match (self.is_readable(), self.is_writable(), self.is_executable()) {
+            (_, true, true) => PAGE_EXECUTE_READWRITE,
+            (true, false, true) => PAGE_EXECUTE_READ,
+            (false, false, true) => PAGE_EXECUTE,
+            (_, true, false) => PAGE_READWRITE,
+            (true, false, false) => PAGE_READONLY,
+            (false, false, false) => PAGE_NOACCESS,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Protection {
+    /// Converts `PROT_*` flags into a [`Protection`].
+    pub fn from_prot(prot: i32) -> Self {
+        let mut protection = Self::NONE;
+        if prot & libc::PROT_READ != 0 {
+            protection = protection | Self::READ;
+        }
+        if prot & libc::PROT_WRITE != 0 {
+            protection = protection | Self::WRITE;
+        }
+        if prot & libc::PROT_EXEC != 0 {
+            protection = protection | Self::EXECUTE;
+        }
+        protection
+    }
+
+    pub fn to_prot(self) -> i32 {
+        let mut prot = libc::PROT_NONE;
+        if self.is_readable() {
+            prot |= libc::PROT_READ;
+        }
+        if self.is_writable() {
+            prot |= libc::PROT_WRITE;
+        }
+        if self.is_executable() {
+            prot |= libc::PROT_EXEC;
+        }
+        prot
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RegionState {
+    #[default]
+    Committed,
+    Reserved,
+    Free,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RegionKind {
+    /// Anonymous memory owned by the process, heaps and stacks live here.
+    #[default]
+    Private,
+    /// A mapped view of a file or shared memory section.
+    Mapped,
+    /// A mapped executable or shared library.
+    Image,
+}
+
+/// Plain old data that is valid for any bit pattern, so it can be read from and written to raw memory.
+///
+/// # Safety
+/// The type must be `#[repr(C)]` or a primitive, contain no padding, references, pointers to local
+/// memory, `bool`, `char` or enums.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($type:ty),*) => {
+        $(unsafe impl Pod for $type {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// A contiguous range of pages sharing the same protection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub base_address: usize,
+    pub size: usize,
+    pub protection: Protection,
+    pub state: RegionState,
+    pub kind: RegionKind,
+    /// File backing the region, if known.
+    pub path: Option<String>,
+}
+
+impl MemoryRegion {
+    #[inline]
+    pub fn end_address(&self) -> usize {
+        self.base_address + self.size
+    }
+
+    #[inline]
+    pub fn contains(&self, address: usize) -> bool {
+        address >= self.base_address && address < self.end_address()
+    }
+
+    #[inline]
+    pub fn is_committed(&self) -> bool {
+        self.state == RegionState::Committed
+    }
+
+    #[inline]
+    pub fn is_readable(&self) -> bool {
+        self.is_committed() && self.protection.is_readable()
+    }
+
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        self.is_committed() && self.protection.is_writable()
+    }
+
+    #[inline]
+    pub fn is_executable(&self) -> bool {
+        self.is_committed() && self.protection.is_executable()
+    }
+
+    #[inline]
+    pub fn is_private(&self) -> bool {
+        self.kind == RegionKind::Private
+    }
+
+    #[inline]
+    pub fn is_image(&self) -> bool {
+        self.kind == RegionKind::Image
+    }
+}
+
+#[cfg(windows)]
+impl From<&MEMORY_BASIC_INFORMATION> for MemoryRegion {
+    fn from(memory_info: &MEMORY_BASIC_INFORMATION) -> Self {
+        let protection = if memory_info.State == MEM_COMMIT {
+            Protection::from_page_flags(memory_info.Protect)
+        } else {
+            Protection::NONE
+        };
+        let state = match memory_info.State {
+            MEM_COMMIT => RegionState::Committed,
+            MEM_RESERVE => RegionState::Reserved,
+            _ => RegionState::Free,
+        };
+        let kind = match memory_info.Type {
+            MEM_IMAGE => RegionKind::Image,
+            MEM_MAPPED => RegionKind::Mapped,
+            _ => RegionKind::Private,
+        };
+        Self {
+            base_address: memory_info.BaseAddress as usize,
+            size: memory_info.RegionSize,
+            protection,
+            state,
+            kind,
+            path: None,
+        }
+    }
+}
+
+/// Granularity of partial reads, a failed read loses at most this many bytes.
+pub const PAGE_SIZE: usize = 0x1000;
+
+/// Bytes read by [`MemoryAccess::read_partial`], unreadable pages are zero-filled and left out of `valid_ranges`.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct PartialRead {
+    pub base_address: usize,
+    pub data: Vec<u8>,
+    /// Sorted, non-adjacent ranges of `data` that were actually read.
+    pub valid_ranges: Vec<Range<usize>>,
+}
+
+impl PartialRead {
+    /// Whether every byte of `range` was read.
+    pub fn is_valid(&self, range: Range<usize>) -> bool {
+        self.valid_ranges
+            .iter()
+            .any(|valid| valid.start <= range.start && range.end <= valid.end)
+    }
+
+    /// Whether all of `data` was read.
+    pub fn is_complete(&self) -> bool {
+        self.data.is_empty() || self.is_valid(0..self.data.len())
+    }
+
+    /// Iterates over the valid ranges as `(offset, bytes)`.
+    pub fn valid_slices(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        self.valid_ranges
+            .iter()
+            .map(|range| (range.start, &self.data[range.clone()]))
+    }
+
+    fn add_valid_range(&mut self, range: Range<usize>) {
+        match self.valid_ranges.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => self.valid_ranges.push(range),
+        }
+    }
+
+    /// Reads `start..end` of one readable region, one page at a time if reading it at once fails.
+    fn read_span<M: MemoryAccess + ?Sized>(&mut self, memory: &M, start: usize, end: usize) {
+        let offset = start - self.base_address;
+        let span = offset..end - self.base_address;
+        if memory
+            .read_bytes(start, &mut self.data[span.clone()])
+            .is_ok()
+        {
+            self.add_valid_range(span);
+            return;
+        }
+
+        let mut page_start = start;
+        while page_start < end {
+            let page_end = next_page(page_start).min(end);
+            let page = page_start - self.base_address..page_end - self.base_address;
+            if memory
+                .read_bytes(page_start, &mut self.data[page.clone()])
+                .is_ok()
+            {
+                self.add_valid_range(page);
+            } else {
+                self.data[page].fill(0);
+            }
+            page_start = page_end;
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn next_page(address: usize) -> usize {
+    (address | (PAGE_SIZE - 1)).saturating_add(1)
+}
+
+/// Implements [`MemoryAccess::read_partial`] with `query` looking up the region containing an address.
+fn read_partial_with<M: MemoryAccess + ?Sized>(
+    memory: &M,
+    address: usize,
+    len: usize,
+    query: impl Fn(usize) -> Result<MemoryRegion>,
+) -> Result<PartialRead> {
+    let end = address
+        .checked_add(len)
+        .ok_or_else(|| anyhow!("partial read at {:#0x} overflows", address))?;
+    let mut partial = PartialRead {
+        base_address: address,
+        data: vec![0u8; len],
+        valid_ranges: vec![],
+    };
+
+    let mut current = address;
+    while current < end {
+        let region = match query(current) {
+            Ok(region) if region.end_address() > current => region,
+            _ => {
+                current = next_page(current).min(end);
+                continue;
+            }
+        };
+        let span_end = region.end_address().min(end);
+        if region.is_readable() {
+            partial.read_span(memory, current, span_end);
+        }
+        current = span_end;
+    }
+    Ok(partial)
+}
+
+/// Backend used to access the memory of a process.
+///
+/// Implemented for the current process by [`LocalMemory`] and for other processes by [`RemoteMemory`],
+/// so [`Module`], [`Process`] and the pattern scanner work the same way injected or external.
+pub trait MemoryAccess {
+    /// Reads `buffer.len()` bytes starting at `address` into `buffer`.
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()>;
+
+    /// Writes `data` starting at `address`.
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()>;
+
+    /// Returns the region that contains `address`.
+    fn query_region(&self, address: usize) -> Result<MemoryRegion>;
+
+    /// Changes the protection of the pages spanning `address..address + size` and returns the previous protection.
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection>;
+
+    /// Like [`MemoryAccess::protect`], but returns the previous protection as raw backend flags.
+    ///
+    /// Restoring them with [`MemoryAccess::restore_protection`] keeps what [`Protection`] cannot express, like
+    /// `PAGE_GUARD` and `PAGE_WRITECOPY` on windows.
+    fn protect_raw(&self, address: usize, size: usize, protection: Protection) -> Result<u32> {
+        Ok(self.protect(address, size, protection)?.bits() as u32)
+    }
+
+    /// Sets flags returned by [`MemoryAccess::protect_raw`] on the pages spanning `address..address + size`.
+    fn restore_protection(&self, address: usize, size: usize, raw: u32) -> Result<()> {
+        self.protect(address, size, Protection::from_bits(raw as u8))?;
+        Ok(())
+    }
+
+    /// Whether reads and writes succeed regardless of page protection, so it never has to be changed for them.
+    fn ignores_protection(&self) -> bool {
+        false
+    }
+
+    /// Allocates `size` bytes of zeroed memory with `protection` and returns its address.
+    ///
+    /// With a `preferred` address the allocation fails instead of landing anywhere else. Backends that cannot
+    /// allocate return an error.
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        let _ = (size, protection, preferred);
+        Err(anyhow!("this memory backend cannot allocate memory"))
+    }
+
+    /// Frees memory returned by [`MemoryAccess::allocate`], `size` must be the allocated size.
+    fn free(&self, address: usize, size: usize) -> Result<()> {
+        let _ = size;
+        Err(anyhow!(
+            "cannot free {:#0x}, this memory backend cannot allocate memory",
+            address
+        ))
+    }
+
+    /// Returns every region that is not free, ordered by address.
+    ///
+    /// The default implementation walks the address space with [`MemoryAccess::query_region`].
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        let mut regions = vec![];
+        let mut address = 0usize;
+        while let Ok(region) = self.query_region(address) {
+            let next_address = region.base_address.checked_add(region.size);
+            if region.state != RegionState::Free {
+                regions.push(region);
+            }
+            match next_address {
+                Some(next_address) if next_address > address => address = next_address,
+                _ => break,
+            }
+        }
+        Ok(regions)
+    }
+
+    /// Reads `len` bytes starting at `address`, skipping whatever is not committed and readable.
+    ///
+    /// The range is split at region boundaries, a readable region that still fails to read is retried page by page.
+    fn read_partial(&self, address: usize, len: usize) -> Result<PartialRead> {
+        read_partial_with(self, address, len, |address| self.query_region(address))
+    }
+
+    /// Performs every `(address, buffer)` read and returns which of them succeeded.
+    ///
+    /// Backends override this to issue all reads in a single system call where possible.
+    fn read_scatter(&self, reads: &mut [(usize, &mut [u8])]) -> Vec<bool> {
+        reads
+            .iter_mut()
+            .map(|(address, buffer)| self.read_bytes(*address, buffer).is_ok())
+            .collect()
+    }
+
+    /// Size of a pointer in the target, 4 for 32-bit and 8 for 64-bit processes.
+    fn pointer_width(&self) -> usize {
+        mem::size_of::<usize>()
+    }
+
+    /// Reads a pointer sized according to [`MemoryAccess::pointer_width`].
+    fn read_pointer(&self, address: usize) -> Result<usize> {
+        let mut bytes = [0u8; 8];
+        let pointer_width = self.pointer_width().min(bytes.len());
+        self.read_bytes(address, &mut bytes[..pointer_width])?;
+        Ok(u64::from_le_bytes(bytes) as usize)
+    }
+
+    fn read<T: Pod>(&self, address: usize) -> Result<T>
+    where
+        Self: Sized,
+    {
+        let mut value = mem::MaybeUninit::<T>::zeroed();
+        let buffer = unsafe {
+            std::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>())
+        };
+        self.read_bytes(address, buffer)?;
+        Ok(unsafe { value.assume_init() })
+    }
+
+    fn write<T: Pod>(&self, address: usize, value: T) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let data = unsafe {
+            std::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>())
+        };
+        self.write_bytes(address, data)
+    }
+}
+
+/// Accesses the memory of the current process directly through pointers.
+///
+/// Addresses are trusted as is, reading or writing unmapped memory will crash the process.
+#[cfg(all(any(windows, target_os = "linux"), feature = "internal"))]
+#[derive(Default, Clone, Copy, Debug)]
+pub struct LocalMemory;
+
+#[cfg(all(any(windows, target_os = "linux"), feature = "internal"))]
+impl MemoryAccess for LocalMemory {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        unsafe { ptr::copy(address as *const u8, buffer.as_mut_ptr(), buffer.len()) };
+        Ok(())
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        unsafe { ptr::copy(data.as_ptr(), address as *mut u8, data.len()) };
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        let memory_info = virtual_query(address as *const ())?;
+        Ok(MemoryRegion::from(&memory_info))
+    }
+
+    #[cfg(windows)]
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection> {
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        let mut old_protect = 0;
+        virtual_protect_ex(
+            unsafe { GetCurrentProcess() },
+            address,
+            size,
+            protection.to_page_flags(),
+            &mut old_protect,
+        )?;
+        Ok(Protection::from_page_flags(old_protect))
+    }
+
+    #[cfg(windows)]
+    fn protect_raw(&self, address: usize, size: usize, protection: Protection) -> Result<u32> {
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        let mut old_protect = 0;
+        virtual_protect_ex(
+            unsafe { GetCurrentProcess() },
+            address,
+            size,
+            protection.to_page_flags(),
+            &mut old_protect,
+        )?;
+        Ok(old_protect)
+    }
+
+    #[cfg(windows)]
+    fn restore_protection(&self, address: usize, size: usize, raw: u32) -> Result<()> {
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        let mut old_protect = 0;
+        virtual_protect_ex(
+            unsafe { GetCurrentProcess() },
+            address,
+            size,
+            raw,
+            &mut old_protect,
+        )
+    }
+
+    #[cfg(windows)]
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        query_process_regions(unsafe { GetCurrentProcess() })
+    }
+
+    #[cfg(windows)]
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        virtual_alloc_ex_at(
+            unsafe { GetCurrentProcess() },
+            preferred,
+            size,
+            protection.to_page_flags(),
+        )
+    }
+
+    #[cfg(windows)]
+    fn free(&self, address: usize, _size: usize) -> Result<()> {
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        virtual_free_ex(unsafe { GetCurrentProcess() }, address)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        query_process_region(std::process::id(), address)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        query_process_regions(std::process::id())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn read_partial(&self, address: usize, len: usize) -> Result<PartialRead> {
+        let regions = query_process_regions(std::process::id())?;
+        read_partial_with(self, address, len, |address| {
+            Ok(region_at(&regions, address))
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection> {
+        let old_protection = self.query_region(address)?.protection;
+        mprotect(address, size, protection.to_prot())?;
+        Ok(old_protection)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        mmap(size, protection.to_prot(), preferred)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn free(&self, address: usize, size: usize) -> Result<()> {
+        munmap(address, size)
+    }
+}
+
+/// Accesses the memory of another process through its handle.
+///
+/// Clones share the handle, it is closed once the last clone is dropped.
+#[cfg(all(windows, feature = "external"))]
+#[derive(Clone, Debug)]
+pub struct RemoteMemory {
+    handle: Arc<OwnedHandle>,
+    pointer_width: usize,
+}
+
+#[cfg(all(windows, feature = "external"))]
+#[derive(Debug)]
+struct OwnedHandle(HANDLE);
+
+#[cfg(all(windows, feature = "external"))]
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        close_handle(self.0);
+    }
+}
+
+#[cfg(all(windows, feature = "external"))]
+impl RemoteMemory {
+    /// Takes ownership of `handle`.
+    pub fn new(handle: HANDLE) -> Self {
+        let pointer_width = if is_wow64_process(handle) {
+            4
+        } else {
+            mem::size_of::<usize>()
+        };
+        Self {
+            handle: Arc::new(OwnedHandle(handle)),
+            pointer_width,
+        }
+    }
+
+    #[inline]
+    pub fn handle(&self) -> HANDLE {
+        self.handle.0
+    }
+}
+
+#[cfg(all(windows, feature = "external"))]
+impl MemoryAccess for RemoteMemory {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        read_process_memory(self.handle(), address, buffer.as_mut_ptr(), buffer.len())
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        write_process_memory(self.handle(), address, data.as_ptr() as *mut u8, data.len())
+    }
+
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        let memory_info = virtual_query_ex(self.handle(), address)?;
+        Ok(MemoryRegion::from(&memory_info))
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        query_process_regions(self.handle())
+    }
+
+    fn pointer_width(&self) -> usize {
+        self.pointer_width
+    }
+
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection> {
+        let mut old_protect = 0;
+        virtual_protect_ex(
+            self.handle(),
+            address,
+            size,
+            protection.to_page_flags(),
+            &mut old_protect,
+        )?;
+        Ok(Protection::from_page_flags(old_protect))
+    }
+
+    fn protect_raw(&self, address: usize, size: usize, protection: Protection) -> Result<u32> {
+        let mut old_protect = 0;
+        virtual_protect_ex(
+            self.handle(),
+            address,
+            size,
+            protection.to_page_flags(),
+            &mut old_protect,
+        )?;
+        Ok(old_protect)
+    }
+
+    fn restore_protection(&self, address: usize, size: usize, raw: u32) -> Result<()> {
+        let mut old_protect = 0;
+        virtual_protect_ex(self.handle(), address, size, raw, &mut old_protect)
+    }
+
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        virtual_alloc_ex_at(self.handle(), preferred, size, protection.to_page_flags())
+    }
+
+    fn free(&self, address: usize, _size: usize) -> Result<()> {
+        virtual_free_ex(self.handle(), address)
+    }
+}
+
+/// Accesses the memory of another process through its id.
+#[cfg(all(target_os = "linux", feature = "external"))]
+#[derive(Clone, Copy, Debug)]
+pub struct RemoteMemory {
+    process_id: u32,
+    pointer_width: usize,
+}
+
+#[cfg(all(target_os = "linux", feature = "external"))]
+impl RemoteMemory {
+    pub fn new(process_id: u32) -> Self {
+        Self {
+            process_id,
+            pointer_width: get_process_pointer_width(process_id).unwrap_or(mem::size_of::<usize>()),
+        }
+    }
+
+    #[inline]
+    pub fn process_id(&self) -> u32 {
+        self.process_id
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "external"))]
+impl MemoryAccess for RemoteMemory {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        read_process_memory(self.process_id, address, buffer.as_mut_ptr(), buffer.len())
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        write_process_memory(
+            self.process_id,
+            address,
+            data.as_ptr() as *mut u8,
+            data.len(),
+        )
+    }
+
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        query_process_region(self.process_id, address)
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        query_process_regions(self.process_id)
+    }
+
+    /// Parses `/proc/<pid>/maps` once instead of once per region.
+    fn read_partial(&self, address: usize, len: usize) -> Result<PartialRead> {
+        let regions = query_process_regions(self.process_id)?;
+        read_partial_with(self, address, len, |address| {
+            Ok(region_at(&regions, address))
+        })
+    }
+
+    /// Uses one `process_vm_readv` call for all reads, a failed read is retried on its own and skipped.
+    fn read_scatter(&self, reads: &mut [(usize, &mut [u8])]) -> Vec<bool> {
+        let mut results = vec![false; reads.len()];
+        let mut start = 0;
+        while start < reads.len() {
+            let completed = read_process_memory_vectored(self.process_id, &mut reads[start..]);
+            results[start..start + completed].fill(true);
+            start += completed;
+            if let Some((address, buffer)) = reads.get_mut(start) {
+                results[start] = self.read_bytes(*address, buffer).is_ok();
+                start += 1;
+            }
+        }
+        results
+    }
+
+    fn pointer_width(&self) -> usize {
+        self.pointer_width
+    }
+
+    /// Runs `mprotect` inside the target through ptrace.
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection> {
+        let old_protection = self.query_region(address)?.protection;
+        mprotect_remote(self.process_id, address, size, protection.to_prot())?;
+        Ok(old_protection)
+    }
+
+    /// `/proc/<pid>/mem` writes through any protection, which is what writes fall back to.
+    fn ignores_protection(&self) -> bool {
+        true
+    }
+
+    /// Runs `mmap` inside the target through ptrace.
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        mmap_remote(self.process_id, size, protection.to_prot(), preferred)
+    }
+
+    /// Runs `munmap` inside the target through ptrace.
+    fn free(&self, address: usize, size: usize) -> Result<()> {
+        munmap_remote(self.process_id, address, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> MockMemory {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![1; 0x1000], Protection::READ)
+            .unwrap();
+        memory
+            .add_region(0x2000, vec![2; 0x1000], Protection::READ_WRITE)
+            .unwrap();
+        memory
+            .add_region(0x3000, vec![3; 0x1000], Protection::NONE)
+            .unwrap();
+        memory
+    }
+
+    #[test]
+    fn partial_read_merges_adjacent_regions() {
+        let partial = memory().read_partial(0x1800, 0x1000).unwrap();
+        assert_eq!(partial.valid_ranges, vec![0..0x1000]);
+        assert!(partial.is_complete());
+        assert_eq!(partial.data[0x7FF], 1);
+        assert_eq!(partial.data[0x800], 2);
+    }
+
+    #[test]
+    fn partial_read_starts_in_unmapped_memory() {
+        let partial = memory().read_partial(0x800, 0x1000).unwrap();
+        assert_eq!(partial.valid_ranges, vec![0x800..0x1000]);
+        assert!(partial.data[..0x800].iter().all(|&byte| byte == 0));
+        assert!(partial.data[0x800..].iter().all(|&byte| byte == 1));
+        assert!(!partial.is_complete());
+        assert!(partial.is_valid(0x900..0xA00));
+    }
+
+    #[test]
+    fn partial_read_ends_inside_a_page() {
+        let partial = memory().read_partial(0x2F00, 0x180).unwrap();
+        assert_eq!(partial.valid_ranges, vec![0..0x100]);
+        assert_eq!(partial.data.len(), 0x180);
+        assert!(partial.data[..0x100].iter().all(|&byte| byte == 2));
+        assert!(
+            partial.data[0x100..].iter().all(|&byte| byte == 0),
+            "no access"
+        );
+
+        let partial = memory().read_partial(0x1000, 0x10).unwrap();
+        assert_eq!(partial.valid_ranges, vec![0..0x10]);
+        assert!(memory().read_partial(usize::MAX, 2).is_err());
+    }
+}
@@ -119,3 +119,76 @@ pub fn create_hook(target: *mut (), detour: *mut ()) -> Result<*mut c_void> {
 
     Ok(original_function)
 }
+
+/// Removes a hook created with [`create_hook`], disabling it first if needed.
+#[cfg(all(windows, feature = "minhook"))]
+pub fn remove_hook(target: *mut ()) -> Result<()> {
+    let status = unsafe { minhook_sys::MH_RemoveHook(target as *mut c_void) };
+    if status != MH_OK {
+        return Err(anyhow!(format!(
+            "Error occured when removing hook {:#0x}, status code: {:?}",
+            target as usize, status
+        )));
+    }
+    Ok(())
+}
+
+/// Owned minhook hook that can be put in a [`crate::registry::Registry`], removed again on drop.
+#[cfg(all(windows, feature = "minhook"))]
+pub struct Hook {
+    target: *mut (),
+    trampoline: *mut c_void,
+    enabled: bool,
+}
+
+#[cfg(all(windows, feature = "minhook"))]
+unsafe impl Send for Hook {}
+
+#[cfg(all(windows, feature = "minhook"))]
+impl Hook {
+    /// Creates the hook in disabled state.
+    pub fn create(target: *mut (), detour: *mut ()) -> Result<Self> {
+        Ok(Self {
+            target,
+            trampoline: create_hook(target, detour)?,
+            enabled: false,
+        })
+    }
+
+    #[inline]
+    pub fn target(&self) -> *mut () {
+        self.target
+    }
+
+    /// Calls the original target function.
+    #[inline]
+    pub fn trampoline(&self) -> *mut c_void {
+        self.trampoline
+    }
+}
+
+#[cfg(all(windows, feature = "minhook"))]
+impl crate::registry::Toggle for Hook {
+    fn enable(&mut self) -> Result<()> {
+        enable_hook(self.target)?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    fn disable(&mut self) -> Result<()> {
+        disable_hook(self.target)?;
+        self.enabled = false;
+        Ok(())
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+#[cfg(all(windows, feature = "minhook"))]
+impl Drop for Hook {
+    fn drop(&mut self) {
+        let _ = remove_hook(self.target);
+    }
+}
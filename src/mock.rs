@@ -0,0 +1,387 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, RwLock,
+};
+
+use crate::*;
+
+#[derive(Clone, Debug)]
+struct MockRegion {
+    base_address: usize,
+    data: Vec<u8>,
+    protection: Protection,
+}
+
+impl MockRegion {
+    #[inline]
+    fn end_address(&self) -> usize {
+        self.base_address + self.data.len()
+    }
+}
+
+/// In-memory backend built from byte regions, used to test features against captured memory instead of a live process.
+///
+/// Reads require [`Protection::READ`] and writes require [`Protection::WRITE`] on every touched byte.
+/// Clones share the same regions.
+#[derive(Clone, Debug)]
+pub struct MockMemory {
+    regions: Arc<RwLock<Vec<MockRegion>>>,
+    pointer_width: Arc<AtomicUsize>,
+}
+
+impl Default for MockMemory {
+    fn default() -> Self {
+        Self {
+            regions: Default::default(),
+            pointer_width: Arc::new(AtomicUsize::new(mem::size_of::<usize>())),
+        }
+    }
+}
+
+impl MockMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emulates a 32-bit (4) or 64-bit (8) target.
+    pub fn set_pointer_width(&self, pointer_width: usize) {
+        self.pointer_width.store(pointer_width, Ordering::Relaxed);
+    }
+
+    /// Maps `data` at `base_address`. Fails if it overlaps an existing region.
+    pub fn add_region(
+        &self,
+        base_address: usize,
+        data: Vec<u8>,
+        protection: Protection,
+    ) -> Result<()> {
+        if data.is_empty() {
+            return Err(anyhow!("mock region at {:#0x} is empty", base_address));
+        }
+        let end_address = base_address
+            .checked_add(data.len())
+            .ok_or_else(|| anyhow!("mock region at {:#0x} overflows", base_address))?;
+
+        let mut regions = self.regions.write().unwrap();
+        if let Some(other) = regions
+            .iter()
+            .find(|other| base_address < other.end_address() && other.base_address < end_address)
+        {
+            return Err(anyhow!(
+                "mock region {:#0x}..{:#0x} overlaps {:#0x}..{:#0x}",
+                base_address,
+                end_address,
+                other.base_address,
+                other.end_address()
+            ));
+        }
+
+        let index = regions.partition_point(|other| other.base_address < base_address);
+        regions.insert(
+            index,
+            MockRegion {
+                base_address,
+                data,
+                protection,
+            },
+        );
+        Ok(())
+    }
+
+    /// Calls `operation` for every region piece covering `address..address + size`.
+    fn for_each_piece(
+        &self,
+        address: usize,
+        size: usize,
+        required: Protection,
+        mut operation: impl FnMut(&mut MockRegion, std::ops::Range<usize>, usize),
+    ) -> Result<()> {
+        let mut regions = self.regions.write().unwrap();
+        let mut cursor = address;
+        let end_address = address
+            .checked_add(size)
+            .ok_or_else(|| anyhow!("access at {:#0x} overflows", address))?;
+
+        while cursor < end_address {
+            let Some(region) = regions
+                .iter_mut()
+                .find(|region| region.base_address <= cursor && cursor < region.end_address())
+            else {
+                return Err(anyhow!("mock memory at {:#0x} is not mapped", cursor));
+            };
+            if !region.protection.contains(required) {
+                return Err(anyhow!(
+                    "mock memory at {:#0x} has protection {}, {} is required",
+                    cursor,
+                    region.protection,
+                    required
+                ));
+            }
+
+            let piece_end = end_address.min(region.end_address());
+            let range = cursor - region.base_address..piece_end - region.base_address;
+            operation(region, range, cursor - address);
+            cursor = piece_end;
+        }
+        Ok(())
+    }
+
+    /// Splits the region containing `address` so that a region starts exactly at `address`.
+    fn split_at(regions: &mut Vec<MockRegion>, address: usize) {
+        let Some(index) = regions
+            .iter()
+            .position(|region| region.base_address < address && address < region.end_address())
+        else {
+            return;
+        };
+        let region = &mut regions[index];
+        let data = region.data.split_off(address - region.base_address);
+        let protection = region.protection;
+        regions.insert(
+            index + 1,
+            MockRegion {
+                base_address: address,
+                data,
+                protection,
+            },
+        );
+    }
+}
+
+impl MemoryAccess for MockMemory {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        self.for_each_piece(
+            address,
+            buffer.len(),
+            Protection::READ,
+            |region, range, offset| {
+                buffer[offset..offset + range.len()].copy_from_slice(&region.data[range]);
+            },
+        )
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        self.for_each_piece(
+            address,
+            data.len(),
+            Protection::WRITE,
+            |region, range, offset| {
+                let len = range.len();
+                region.data[range].copy_from_slice(&data[offset..offset + len]);
+            },
+        )
+    }
+
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        let regions = self.regions.read().unwrap();
+        let mut gap_start = 0;
+        for region in regions.iter() {
+            if address < region.base_address {
+                break;
+            }
+            if address < region.end_address() {
+                return Ok(MemoryRegion {
+                    base_address: region.base_address,
+                    size: region.data.len(),
+                    protection: region.protection,
+                    ..Default::default()
+                });
+            }
+            gap_start = region.end_address();
+        }
+        let gap_end = regions
+            .iter()
+            .map(|region| region.base_address)
+            .find(|&base_address| base_address > address)
+            .unwrap_or(usize::MAX);
+        Ok(MemoryRegion {
+            base_address: gap_start,
+            size: gap_end - gap_start,
+            state: RegionState::Free,
+            ..Default::default()
+        })
+    }
+
+    /// Like the operating system, the range is widened to page boundaries.
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection> {
+        self.for_each_piece(address, size.max(1), Protection::NONE, |_, _, _| {})?;
+        let start = address & !(PAGE_SIZE - 1);
+        let end = (address + size.max(1)).next_multiple_of(PAGE_SIZE);
+
+        let mut regions = self.regions.write().unwrap();
+        Self::split_at(&mut regions, start);
+        Self::split_at(&mut regions, end);
+
+        let mut old_protection = None;
+        for region in regions
+            .iter_mut()
+            .filter(|region| region.base_address >= start && region.end_address() <= end)
+        {
+            old_protection.get_or_insert(region.protection);
+            region.protection = protection;
+        }
+        Ok(old_protection.unwrap_or_default())
+    }
+
+    /// Places the allocation in the first free gap from `0x10000` unless an address is preferred.
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        if size == 0 {
+            return Err(anyhow!("cannot allocate 0 bytes"));
+        }
+        let size = size.next_multiple_of(PAGE_SIZE);
+        let address = match preferred {
+            Some(address) => address,
+            None => {
+                let regions = self.regions.read().unwrap();
+                let mut address = 0x10000;
+                for region in regions.iter() {
+                    if address + size <= region.base_address {
+                        break;
+                    }
+                    address = address.max(region.end_address().next_multiple_of(PAGE_SIZE));
+                }
+                address
+            }
+        };
+        self.add_region(address, vec![0u8; size], protection)?;
+        Ok(address)
+    }
+
+    fn free(&self, address: usize, size: usize) -> Result<()> {
+        let end = address
+            .saturating_add(size.max(1))
+            .next_multiple_of(PAGE_SIZE);
+        let mut regions = self.regions.write().unwrap();
+        let count = regions.len();
+        regions.retain(|region| region.base_address < address || region.end_address() > end);
+        if regions.len() == count {
+            return Err(anyhow!("mock memory at {:#0x} is not allocated", address));
+        }
+        Ok(())
+    }
+
+    fn pointer_width(&self) -> usize {
+        self.pointer_width.load(Ordering::Relaxed)
+    }
+}
+
+/// A [`Process`] backed by [`MockMemory`].
+pub type MockProcess = Process<MockMemory>;
+
+impl Process<MockMemory> {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            memory: MockMemory::new(),
+            modules: vec![],
+        }
+    }
+
+    pub fn add_region(
+        &mut self,
+        base_address: usize,
+        data: Vec<u8>,
+        protection: Protection,
+    ) -> Result<()> {
+        self.memory.add_region(base_address, data, protection)
+    }
+
+    /// Maps `data` at `base_address` and registers it as a module named `name`.
+    pub fn add_module(
+        &mut self,
+        name: &str,
+        base_address: usize,
+        data: Vec<u8>,
+        protection: Protection,
+    ) -> Result<Module<MockMemory>> {
+        let size = data.len();
+        self.memory.add_region(base_address, data, protection)?;
+        let module = Module {
+            name: name.to_string(),
+            handle: 0,
+            size,
+            base_address,
+            memory: self.memory.clone(),
+        };
+        self.modules.push(module.clone());
+        Ok(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accesses_require_protection() {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![0u8; 0x1000], Protection::READ)
+            .unwrap();
+        assert!(memory.read::<u32>(0x1000).is_ok());
+        assert!(memory.write(0x1000, 1u32).is_err());
+        assert!(memory.read::<u32>(0x1FFE).is_err(), "reads past the region");
+        assert!(memory
+            .add_region(0x1800, vec![0u8; 0x10], Protection::READ)
+            .is_err());
+    }
+
+    #[test]
+    fn protect_splits_regions() {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![0u8; 0x3000], Protection::READ)
+            .unwrap();
+        let old = memory.protect(0x2010, 4, Protection::READ_WRITE).unwrap();
+        assert_eq!(old, Protection::READ);
+        memory.write(0x2010, 7u32).unwrap();
+        assert_eq!(
+            memory.query_region(0x2000).unwrap().size,
+            0x1000,
+            "only the page is changed"
+        );
+        assert!(memory.write(0x1FFC, 1u32).is_err());
+        assert_eq!(memory.regions().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn allocate_and_free() {
+        let memory = MockMemory::new();
+        let address = memory.allocate(10, Protection::READ_WRITE, None).unwrap();
+        assert_eq!(address % PAGE_SIZE, 0);
+        assert_eq!(memory.query_region(address).unwrap().size, PAGE_SIZE);
+        assert!(memory
+            .allocate(10, Protection::READ_WRITE, Some(address))
+            .is_err());
+        memory.free(address, 10).unwrap();
+        assert!(memory.read::<u8>(address).is_err());
+        assert!(memory.free(address, 10).is_err());
+    }
+
+    #[test]
+    fn find_pattern_skips_unreadable_pages() {
+        let mut process = MockProcess::new(1);
+        let mut data = vec![0u8; 0x3000];
+        data[0x1100..0x1104].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        data[0x2200..0x2204].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let module = process
+            .add_module("game.exe", 0x40_0000, data, Protection::READ_EXECUTE)
+            .unwrap();
+        process
+            .memory
+            .protect(0x40_1000, PAGE_SIZE, Protection::NONE)
+            .unwrap();
+
+        assert_eq!(module.find_pattern("DE AD ? EF").unwrap(), Some(0x2200));
+        assert_eq!(
+            module.find_all_patterns("DE AD BE EF").unwrap(),
+            vec![0x2200]
+        );
+        assert_eq!(module.find_pattern("DE AD 00 EF").unwrap(), None);
+    }
+}
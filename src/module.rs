@@ -1,65 +1,64 @@
-use crate::*;
+use crate::{patch::write_with_protection, *};
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "internal"))]
 use windows_sys::Win32::{Foundation::FARPROC, System::LibraryLoader::GetProcAddress};
 
-macro_rules! page_operation {
-    ($address:expr, $protect:expr, $operation:expr) => {{
-        let memory_info = virtual_query($address as *const ())?;
-
-        let mut old_protect = $protect;
-        let is_readable = is_page_readable(&memory_info);
-        if !is_readable {
-            virtual_protect($address as *const (), $protect, &mut old_protect)?;
-        }
-
-        let result = $operation;
-
-        if !is_readable {
-            virtual_protect($address as *const (), old_protect, &mut old_protect)?;
-        }
-
-        Ok(result)
-    }};
-}
+/// Bytes of a module as returned by [`Module::get_module_data`], `base_address` is the module base and offsets
+/// are relative to it.
+pub type ModuleData = PartialRead;
 
 #[derive(Default, Clone, Debug)]
-pub struct Module {
+pub struct Module<M> {
     pub name: String,
-    pub process_handle: HANDLE,
     pub handle: HMODULE,
     pub size: usize,
     pub base_address: usize,
+    pub memory: M,
 }
 
-impl Module {
-    #[cfg(feature = "external")]
-    pub fn get_module_data(&self) -> Result<Vec<u8>> {
-        let mut data: Vec<u8> = Vec::with_capacity(self.size);
-        self.read(self.base_address, data.as_mut_ptr() as *mut Vec<u8>)?;
-        Ok(data)
+impl<M: MemoryAccess> Module<M> {
+    /// Reads the whole module, skipping pages that are not committed and readable.
+    pub fn get_module_data(&self) -> Result<ModuleData> {
+        let module_data = self.memory.read_partial(self.base_address, self.size)?;
+        if module_data.valid_ranges.is_empty() && self.size > 0 {
+            return Err(anyhow!(
+                "no readable pages in module {} at {:#0x}",
+                self.name,
+                self.base_address
+            ));
+        }
+        Ok(module_data)
+    }
+
+    /// Reads a `T` located `offset` bytes after the module base address.
+    ///
+    /// The protection is left alone, use a [`ProtectGuard`] to read pages that are not readable.
+    pub fn read<T: Pod>(&self, offset: usize) -> Result<T> {
+        self.memory.read::<T>(self.base_address + offset)
     }
 
-    /// # Safety
-    #[cfg(feature = "internal")]
-    pub unsafe fn get_module_data(&self) -> Vec<u8> {
-        let mut data = Vec::with_capacity(self.size);
-        let data_pointer = data.as_mut_ptr();
-        data.set_len(self.size);
-        ptr::copy(self.base_address as *const u8, data_pointer, self.size);
-        data
+    /// Writes `value` `offset` bytes after the module base address.
+    ///
+    /// Pages that are not writable, like code, are made writable for the duration of the write unless the backend
+    /// writes through protection anyway.
+    pub fn write<T: Pod>(&self, offset: usize, value: T) -> Result<()> {
+        let data = unsafe {
+            std::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>())
+        };
+        write_with_protection(&self.memory, self.base_address + offset, data)
     }
 }
 
-#[cfg(windows)]
-impl Module {
-    #[cfg(feature = "internal")]
+#[cfg(all(windows, feature = "internal"))]
+impl Module<LocalMemory> {
     pub fn from_name(name: &str) -> Result<Self> {
         let module_handle = get_module_handle(name)?;
-        Self::from_handle(None, module_handle)
+        let mut module = Self::from_handle(module_handle)?;
+        module.name = name.to_string();
+        Ok(module)
     }
 
-    pub fn from_handle(process_handle: Option<HANDLE>, module_handle: HMODULE) -> Result<Self> {
+    pub fn from_handle(module_handle: HMODULE) -> Result<Self> {
         let module_info = get_module_info(module_handle)?;
 
         let size = module_info.SizeOfImage as usize;
@@ -67,61 +66,67 @@ impl Module {
 
         Ok(Self {
             name: String::new(),
-            process_handle: process_handle.unwrap_or_default(),
             handle: module_handle,
             size,
             base_address,
+            memory: LocalMemory,
         })
     }
 
     #[inline]
     pub fn get_function_address(&self, function_name: &str) -> FARPROC {
-        unsafe { GetProcAddress(self.handle, make_lpcstr(function_name)) }
-    }
-
-    #[cfg(feature = "external")]
-    pub fn read<T>(&self, address: usize, buffer: *mut T) -> Result<()> {
-        page_operation!(
-            address,
-            PAGE_READWRITE,
-            read_process_memory(self.process_handle, address, buffer, mem::size_of::<T>())?
-        )
+        let Ok(function_name) = make_lpcstr(function_name) else {
+            return None;
+        };
+        unsafe { GetProcAddress(self.handle, function_name.as_ptr() as *const u8) }
     }
+}
 
-    #[cfg(feature = "external")]
-    pub fn write<T>(&self, address: usize, value: T) -> Result<()> {
-        let mut value = value;
-        page_operation!(
-            address,
-            PAGE_READWRITE,
-            write_process_memory(
-                self.process_handle,
-                address,
-                &mut value,
-                mem::size_of::<T>()
-            )?
-        )
-    }
+#[cfg(all(target_os = "linux", feature = "internal"))]
+impl Module<LocalMemory> {
+    pub fn from_name(name: &str) -> Result<Self> {
+        let module_info = get_module_info(name)?;
+        let handle = get_module_handle(&module_info.path)?;
 
-    #[cfg(feature = "internal")]
-    pub fn read<T>(&self, address: usize) -> Result<*mut T> {
-        page_operation!(
-            address,
-            PAGE_READONLY,
-            (self.base_address + address) as *mut T
-        )
+        Ok(Self {
+            name: name.to_string(),
+            handle,
+            size: module_info.size,
+            base_address: module_info.base_address,
+            memory: LocalMemory,
+        })
     }
 
-    #[cfg(feature = "internal")]
-    pub fn write<T>(&self, address: usize, value: T) -> Result<()> {
-        page_operation!(address, PAGE_READWRITE, unsafe {
-            *((self.base_address + address) as *mut T) = value
-        })
+    #[inline]
+    pub fn get_function_address(&self, function_name: &str) -> FARPROC {
+        let Ok(function_name) = make_lpcstr(function_name) else {
+            return None;
+        };
+        let address = unsafe { libc::dlsym(self.handle as *mut c_void, function_name.as_ptr()) };
+        unsafe { mem::transmute::<*mut c_void, FARPROC>(address) }
     }
 }
 
-impl Drop for Module {
-    fn drop(&mut self) {
-        close_handle(self.handle);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_data_skips_unreadable_pages() {
+        let mut process = MockProcess::new(1);
+        let module = process
+            .add_module("game.exe", 0x10000, vec![1u8; 0x3000], Protection::READ)
+            .unwrap();
+        process
+            .memory
+            .protect(0x11000, 0x1000, Protection::NONE)
+            .unwrap();
+
+        let data = module.get_module_data().unwrap();
+        assert_eq!(data.base_address, module.base_address);
+        assert_eq!(data.data.len(), 0x3000);
+        assert_eq!(data.valid_ranges, [0..0x1000, 0x2000..0x3000]);
+        assert!(data.data[0x1000..0x2000].iter().all(|&byte| byte == 0));
+        assert!(!data.is_valid(0xFFE..0x1002));
     }
 }
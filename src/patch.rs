@@ -0,0 +1,279 @@
+use std::fmt;
+
+use crate::*;
+
+/// x86 `nop` instruction.
+const NOP: u8 = 0x90;
+
+/// Reports that patched memory no longer holds the bytes a [`Patch`] expects, reachable through
+/// [`Error::downcast_ref`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchModifiedError {
+    pub address: usize,
+    pub expected: Vec<u8>,
+    pub found: Vec<u8>,
+}
+
+impl fmt::Display for PatchModifiedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "patch at {:#0x} was modified by someone else: expected {:02X?}, found {:02X?}",
+            self.address, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for PatchModifiedError {}
+
+/// Writes `data`, making the range writable for the duration of the write if needed.
+///
+/// Backends that [ignore protection](MemoryAccess::ignores_protection) write without any protection change.
+pub(crate) fn write_with_protection<M: MemoryAccess + ?Sized>(
+    memory: &M,
+    address: usize,
+    data: &[u8],
+) -> Result<()> {
+    let _guard = ProtectGuard::ensure(memory, address, data.len(), Protection::WRITE)?;
+    memory.write_bytes(address, data)
+}
+
+/// Reversible byte patch that remembers the bytes it replaced.
+///
+/// Before restoring, the patch checks that memory still holds the patched bytes, so it never clobbers a
+/// change made by someone else. An enabled patch restores the original bytes when dropped.
+pub struct Patch<M: MemoryAccess> {
+    memory: M,
+    address: usize,
+    original: Vec<u8>,
+    patched: Vec<u8>,
+    enabled: bool,
+}
+
+impl<M: MemoryAccess> Patch<M> {
+    /// Captures the bytes at `address` without applying the patch yet.
+    pub fn new(memory: M, address: usize, bytes: &[u8]) -> Result<Self> {
+        let mut original = vec![0u8; bytes.len()];
+        memory.read_bytes(address, &mut original)?;
+        Ok(Self {
+            memory,
+            address,
+            original,
+            patched: bytes.to_vec(),
+            enabled: false,
+        })
+    }
+
+    /// Captures the bytes at `address` and applies the patch.
+    pub fn apply(memory: M, address: usize, bytes: &[u8]) -> Result<Self> {
+        let mut patch = Self::new(memory, address, bytes)?;
+        patch.enable()?;
+        Ok(patch)
+    }
+
+    /// Replaces `length` bytes at `address` with x86 `nop` instructions.
+    pub fn nop(memory: M, address: usize, length: usize) -> Result<Self> {
+        Self::apply(memory, address, &vec![NOP; length])
+    }
+
+    #[inline]
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.patched.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.patched.is_empty()
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn original(&self) -> &[u8] {
+        &self.original
+    }
+
+    #[inline]
+    pub fn patched(&self) -> &[u8] {
+        &self.patched
+    }
+
+    fn verify(&self, expected: &[u8]) -> Result<()> {
+        let mut found = vec![0u8; expected.len()];
+        self.memory.read_bytes(self.address, &mut found)?;
+        if found != expected {
+            return Err(PatchModifiedError {
+                address: self.address,
+                expected: expected.to_vec(),
+                found,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Writes the patched bytes, fails if memory no longer holds the original bytes.
+    pub fn enable(&mut self) -> Result<()> {
+        if self.enabled {
+            return Ok(());
+        }
+        self.verify(&self.original)?;
+        write_with_protection(&self.memory, self.address, &self.patched)?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    /// Restores the original bytes, fails if memory no longer holds the patched bytes.
+    pub fn disable(&mut self) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.verify(&self.patched)?;
+        write_with_protection(&self.memory, self.address, &self.original)?;
+        self.enabled = false;
+        Ok(())
+    }
+
+    /// Restores the original bytes without checking what is currently there.
+    pub fn force_disable(&mut self) -> Result<()> {
+        write_with_protection(&self.memory, self.address, &self.original)?;
+        self.enabled = false;
+        Ok(())
+    }
+
+    /// Flips the patch and returns whether it is now enabled.
+    pub fn toggle(&mut self) -> Result<bool> {
+        if self.enabled {
+            self.disable()?;
+        } else {
+            self.enable()?;
+        }
+        Ok(self.enabled)
+    }
+
+    /// Consumes the patch without restoring, leaving the patched bytes in place.
+    pub fn leak(mut self) {
+        self.enabled = false;
+    }
+}
+
+impl<M: MemoryAccess> fmt::Debug for Patch<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Patch")
+            .field("address", &format_args!("{:#X}", self.address))
+            .field("original", &format_args!("{:02X?}", self.original))
+            .field("patched", &format_args!("{:02X?}", self.patched))
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+impl<M: MemoryAccess> Drop for Patch<M> {
+    fn drop(&mut self) {
+        let _ = self.disable();
+    }
+}
+
+impl<M: MemoryAccess + Clone> Process<M> {
+    /// Applies a [`Patch`] of `bytes` at `address`.
+    pub fn patch(&self, address: usize, bytes: &[u8]) -> Result<Patch<M>> {
+        Patch::apply(self.memory.clone(), address, bytes)
+    }
+}
+
+impl<M: MemoryAccess + Clone> Module<M> {
+    /// Applies a [`Patch`] of `bytes` located `offset` bytes after the module base address.
+    pub fn patch(&self, offset: usize, bytes: &[u8]) -> Result<Patch<M>> {
+        Patch::apply(self.memory.clone(), self.base_address + offset, bytes)
+    }
+
+    /// Replaces `length` bytes located `offset` bytes after the module base address with `nop` instructions.
+    pub fn nop(&self, offset: usize, length: usize) -> Result<Patch<M>> {
+        Patch::nop(self.memory.clone(), self.base_address + offset, length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> MockMemory {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![0xAA; 0x1000], Protection::READ_EXECUTE)
+            .unwrap();
+        memory
+    }
+
+    #[test]
+    fn patches_read_only_memory() {
+        let memory = memory();
+        let mut patch = Patch::apply(memory.clone(), 0x1010, &[1, 2, 3]).unwrap();
+        assert!(patch.is_enabled());
+        assert_eq!(patch.original(), [0xAA; 3]);
+        assert_eq!(memory.read::<[u8; 4]>(0x1010).unwrap(), [1, 2, 3, 0xAA]);
+        assert_eq!(
+            memory.query_region(0x1010).unwrap().protection,
+            Protection::READ_EXECUTE,
+            "the protection is restored after the write"
+        );
+
+        assert!(!patch.toggle().unwrap());
+        assert_eq!(memory.read::<[u8; 3]>(0x1010).unwrap(), [0xAA; 3]);
+        patch.enable().unwrap();
+        assert_eq!(memory.read::<[u8; 3]>(0x1010).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn refuses_to_clobber_modified_memory() {
+        let memory = memory();
+        let mut patch = Patch::nop(memory.clone(), 0x1010, 2).unwrap();
+        write_with_protection(&memory, 0x1011, &[0xCC]).unwrap();
+
+        let error = patch.disable().unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<PatchModifiedError>(),
+            Some(&PatchModifiedError {
+                address: 0x1010,
+                expected: vec![NOP, NOP],
+                found: vec![NOP, 0xCC],
+            })
+        );
+        assert!(patch.is_enabled());
+
+        patch.force_disable().unwrap();
+        assert_eq!(memory.read::<[u8; 2]>(0x1010).unwrap(), [0xAA; 2]);
+        write_with_protection(&memory, 0x1010, &[0]).unwrap();
+        assert!(
+            patch.enable().is_err(),
+            "the original bytes changed as well"
+        );
+    }
+
+    #[test]
+    fn restores_on_drop() {
+        let memory = memory();
+        drop(Patch::apply(memory.clone(), 0x1FFE, &[1, 2]).unwrap());
+        assert_eq!(memory.read::<[u8; 2]>(0x1FFE).unwrap(), [0xAA; 2]);
+
+        Patch::apply(memory.clone(), 0x1FFE, &[1, 2])
+            .unwrap()
+            .leak();
+        assert_eq!(memory.read::<[u8; 2]>(0x1FFE).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn reports_protection_failures() {
+        let memory = memory();
+        assert!(write_with_protection(&memory, 0x1FFF, &[1, 2]).is_err());
+        assert_eq!(memory.read::<u8>(0x1FFF).unwrap(), 0xAA);
+    }
+}
@@ -1,21 +1,53 @@
-use {crate::module::Module, anyhow::Result, patternscan::scan_first_match, std::io::Cursor};
+use {
+    crate::{
+        memory::MemoryAccess,
+        module::{Module, ModuleData},
+    },
+    anyhow::Result,
+    patternscan::{scan, scan_first_match},
+    std::io::Cursor,
+};
 
-fn scan_data_for_pattern(data: Vec<u8>, pattern: &str) -> Result<Option<usize>> {
-    let reader = Cursor::new(data);
-    let first_match = scan_first_match(reader, pattern)?;
-    Ok(first_match)
+/// `patternscan` pads its buffer with zeros, so matches running past the end of the data are dropped.
+fn fits(bytes: &[u8], pattern: &str, found: usize) -> bool {
+    found + pattern.split_whitespace().count() <= bytes.len()
 }
 
-impl Module {
-    #[cfg(feature = "external")]
+/// Scans each valid range separately, so matches never include zero-filled unreadable pages.
+fn scan_data_for_pattern(module_data: &ModuleData, pattern: &str) -> Result<Option<usize>> {
+    for (offset, bytes) in module_data.valid_slices() {
+        if let Some(first_match) = scan_first_match(Cursor::new(bytes), pattern)? {
+            if fits(bytes, pattern, first_match) {
+                return Ok(Some(offset + first_match));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn scan_data_for_all_patterns(module_data: &ModuleData, pattern: &str) -> Result<Vec<usize>> {
+    let mut matches = vec![];
+    for (offset, bytes) in module_data.valid_slices() {
+        matches.extend(
+            scan(Cursor::new(bytes), pattern)?
+                .into_iter()
+                .filter(|&found| fits(bytes, pattern, found))
+                .map(|found| offset + found),
+        );
+    }
+    Ok(matches)
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Returns the offset of the first match from the module base address.
     pub fn find_pattern(&self, pattern: &str) -> Result<Option<usize>> {
         let module_data = self.get_module_data()?;
-        scan_data_for_pattern(module_data, pattern)
+        scan_data_for_pattern(&module_data, pattern)
     }
 
-    #[cfg(feature = "internal")]
-    pub fn find_pattern(&self, pattern: &str) -> Result<Option<usize>> {
-        let module_data = unsafe { self.get_module_data() };
-        scan_data_for_pattern(module_data, pattern)
+    /// Returns the offsets of all matches from the module base address.
+    pub fn find_all_patterns(&self, pattern: &str) -> Result<Vec<usize>> {
+        let module_data = self.get_module_data()?;
+        scan_data_for_all_patterns(&module_data, pattern)
     }
 }
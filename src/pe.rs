@@ -0,0 +1,537 @@
+use crate::{memory::next_page, *};
+
+const IMAGE_DOS_SIGNATURE: u16 = 0x5A4D;
+const IMAGE_NT_SIGNATURE: u32 = 0x0000_4550;
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10B;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20B;
+const IMAGE_SIZEOF_SECTION_HEADER: usize = 40;
+const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+/// Largest image [`map_pe_file`] maps, far beyond any real executable.
+const MAX_IMAGE_SIZE: usize = 0x4000_0000;
+
+/// Location of the headers of a PE image mapped at `base_address`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeHeaders {
+    pub base_address: usize,
+    pub nt_headers: usize,
+    pub is_64_bit: bool,
+}
+
+impl PeHeaders {
+    pub fn parse<M: MemoryAccess>(memory: &M, base_address: usize) -> Result<Self> {
+        if memory.read::<u16>(base_address)? != IMAGE_DOS_SIGNATURE {
+            return Err(anyhow!("no PE image at {:#0x}", base_address));
+        }
+        let nt_headers = base_address + memory.read::<u32>(base_address + 0x3C)? as usize;
+        if memory.read::<u32>(nt_headers)? != IMAGE_NT_SIGNATURE {
+            return Err(anyhow!(
+                "invalid NT headers signature at {:#0x}",
+                nt_headers
+            ));
+        }
+        let is_64_bit = match memory.read::<u16>(nt_headers + 0x18)? {
+            IMAGE_NT_OPTIONAL_HDR32_MAGIC => false,
+            IMAGE_NT_OPTIONAL_HDR64_MAGIC => true,
+            magic => {
+                return Err(anyhow!(
+                    "unknown optional header magic {:#0x} at {:#0x}",
+                    magic,
+                    nt_headers
+                ))
+            }
+        };
+        Ok(Self {
+            base_address,
+            nt_headers,
+            is_64_bit,
+        })
+    }
+
+    /// Preferred load address from the optional header.
+    pub fn image_base<M: MemoryAccess>(&self, memory: &M) -> Result<usize> {
+        if self.is_64_bit {
+            Ok(memory.read::<u64>(self.nt_headers + 0x30)? as usize)
+        } else {
+            Ok(memory.read::<u32>(self.nt_headers + 0x34)? as usize)
+        }
+    }
+
+    /// Size of the image once mapped, from the optional header.
+    pub fn size_of_image<M: MemoryAccess>(&self, memory: &M) -> Result<usize> {
+        Ok(memory.read::<u32>(self.nt_headers + 0x50)? as usize)
+    }
+
+    /// Alignment of sections in memory, the tail of a section up to it is mapped with the section.
+    pub fn section_alignment<M: MemoryAccess>(&self, memory: &M) -> Result<usize> {
+        Ok(memory.read::<u32>(self.nt_headers + 0x38)? as usize)
+    }
+
+    /// Size of the headers, which are mapped as they are in the file.
+    pub fn size_of_headers<M: MemoryAccess>(&self, memory: &M) -> Result<usize> {
+        Ok(memory.read::<u32>(self.nt_headers + 0x54)? as usize)
+    }
+
+    /// Reads the section table.
+    pub fn sections<M: MemoryAccess>(&self, memory: &M) -> Result<Vec<PeSection>> {
+        let number_of_sections = memory.read::<u16>(self.nt_headers + 0x6)? as usize;
+        let size_of_optional_header = memory.read::<u16>(self.nt_headers + 0x14)? as usize;
+        let table = self.nt_headers + 0x18 + size_of_optional_header;
+
+        let mut headers = vec![0u8; number_of_sections * IMAGE_SIZEOF_SECTION_HEADER];
+        memory.read_bytes(table, &mut headers)?;
+        Ok(headers
+            .chunks_exact(IMAGE_SIZEOF_SECTION_HEADER)
+            .map(|header| {
+                let field = |offset: usize| {
+                    u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap())
+                };
+                let name = &header[..8];
+                let name_length = name.iter().position(|&byte| byte == 0).unwrap_or(8);
+                PeSection {
+                    name: String::from_utf8_lossy(&name[..name_length]).into_owned(),
+                    virtual_size: field(8) as usize,
+                    virtual_address: field(12) as usize,
+                    raw_size: field(16) as usize,
+                    raw_offset: field(20) as usize,
+                    characteristics: field(36),
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the RVA and size of the data directory at `index`.
+    pub fn data_directory<M: MemoryAccess>(&self, memory: &M, index: usize) -> Result<(u32, u32)> {
+        let directories = self.nt_headers + 0x18 + if self.is_64_bit { 112 } else { 96 };
+        let entry = directories + index * 8;
+        Ok((memory.read::<u32>(entry)?, memory.read::<u32>(entry + 4)?))
+    }
+}
+
+/// Entry of the section table, addresses are relative to the image base.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeSection {
+    pub name: String,
+    pub virtual_address: usize,
+    pub virtual_size: usize,
+    pub raw_offset: usize,
+    pub raw_size: usize,
+    pub characteristics: u32,
+}
+
+impl PeSection {
+    /// Size the section occupies once mapped, linkers leave the virtual size zero at times.
+    #[inline]
+    pub fn mapped_size(&self) -> usize {
+        if self.virtual_size == 0 {
+            self.raw_size
+        } else {
+            self.virtual_size
+        }
+    }
+
+    #[inline]
+    pub fn is_executable(&self) -> bool {
+        self.characteristics & (IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_CNT_CODE) != 0
+    }
+
+    /// Protection the loader applies to the section.
+    pub fn protection(&self) -> Protection {
+        [
+            (IMAGE_SCN_MEM_READ, Protection::READ),
+            (IMAGE_SCN_MEM_WRITE, Protection::WRITE),
+            (IMAGE_SCN_MEM_EXECUTE, Protection::EXECUTE),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| self.characteristics & flag != 0)
+        .fold(Protection::NONE, |protection, (_, value)| {
+            protection | value
+        })
+    }
+}
+
+/// Run of `0xCC` or `0x00` filler bytes inside an executable section.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeCave {
+    pub address: usize,
+    pub len: usize,
+    /// The filler byte, `0xCC` or `0x00`.
+    pub byte: u8,
+    pub section: String,
+    /// Protection of the cave at the time it was found.
+    pub protection: Protection,
+}
+
+impl CodeCave {
+    #[inline]
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.address..self.address + self.len
+    }
+}
+
+/// Finds runs of at least `min_length` identical `0xCC` or `0x00` bytes in `data`.
+fn find_filler_runs(data: &[u8], min_length: usize) -> Vec<(std::ops::Range<usize>, u8)> {
+    let mut runs = vec![];
+    let mut start = 0;
+    while start < data.len() {
+        let byte = data[start];
+        let length = data[start..]
+            .iter()
+            .position(|&other| other != byte)
+            .unwrap_or(data.len() - start);
+        if matches!(byte, 0xCC | 0x00) && length >= min_length.max(1) {
+            runs.push((start..start + length, byte));
+        }
+        start += length;
+    }
+    runs
+}
+
+/// Finds code caves in the executable sections of the image at `base_address`.
+pub fn find_code_caves<M: MemoryAccess>(
+    memory: &M,
+    base_address: usize,
+    min_length: usize,
+) -> Result<Vec<CodeCave>> {
+    let headers = PeHeaders::parse(memory, base_address)?;
+    let section_alignment = headers.section_alignment(memory)?.max(1);
+    let size_of_image = headers.size_of_image(memory)?;
+    let mut caves = vec![];
+    let mut region: Option<MemoryRegion> = None;
+    for section in headers.sections(memory)? {
+        if !section.is_executable() {
+            continue;
+        }
+        // the zeroed tail after the section data is the usual place for a cave
+        let end = (section.virtual_address + section.mapped_size())
+            .next_multiple_of(section_alignment)
+            .min(size_of_image);
+        let size = end.saturating_sub(section.virtual_address);
+        let data = memory.read_partial(base_address + section.virtual_address, size)?;
+        for (offset, bytes) in data.valid_slices() {
+            for (range, byte) in find_filler_runs(bytes, min_length) {
+                let address = data.base_address + offset + range.start;
+                // a section is usually a single region, so this queries about once per section
+                let current = match region.take() {
+                    Some(region) if region.contains(address) => region,
+                    _ => memory.query_region(address)?,
+                };
+                caves.push(CodeCave {
+                    address,
+                    len: range.len(),
+                    byte,
+                    section: section.name.clone(),
+                    protection: current.protection,
+                });
+                region = Some(current);
+            }
+        }
+    }
+    Ok(caves)
+}
+
+/// Maps a PE file the way the loader would, into [`MockMemory`] at its preferred image base.
+///
+/// Sections get the protection from their characteristics. Relocations and imports are not processed.
+pub fn map_pe_file(name: &str, file: &[u8]) -> Result<Module<MockMemory>> {
+    let raw = MockMemory::new();
+    raw.add_region(0, file.to_vec(), Protection::READ)?;
+    let headers = PeHeaders::parse(&raw, 0)?;
+    let image_base = headers.image_base(&raw)?;
+    let size_of_image = headers.size_of_image(&raw)?;
+    let mut sections = headers.sections(&raw)?;
+    sections.sort_by_key(|section| section.virtual_address);
+
+    // the size comes from the header, so it is checked against the sections before anything is allocated
+    let section_alignment = headers.section_alignment(&raw)?.max(1);
+    let extent = sections
+        .iter()
+        .map(|section| {
+            section
+                .virtual_address
+                .saturating_add(section.mapped_size())
+        })
+        .fold(headers.size_of_headers(&raw)?, usize::max)
+        .checked_next_multiple_of(section_alignment)
+        .unwrap_or(usize::MAX);
+    if size_of_image > MAX_IMAGE_SIZE || size_of_image > extent {
+        return Err(anyhow!(
+            "PE file {name} claims an image of {size_of_image:#0x} bytes, its sections end at {extent:#0x}"
+        ));
+    }
+    if image_base.checked_add(size_of_image).is_none() {
+        return Err(anyhow!(
+            "PE file {name} has an image base {image_base:#0x} that overflows the address space"
+        ));
+    }
+
+    let mut image = vec![0u8; size_of_image];
+    let copy = |image: &mut [u8], destination: usize, source: usize, size: usize| -> Result<()> {
+        let source = file.get(source..source.saturating_add(size));
+        let destination = image.get_mut(destination..destination.saturating_add(size));
+        match (source, destination) {
+            (Some(source), Some(destination)) => {
+                destination.copy_from_slice(source);
+                Ok(())
+            }
+            _ => Err(anyhow!(
+                "PE file {name} has a section outside of the file or image"
+            )),
+        }
+    };
+    let headers_size = headers.size_of_headers(&raw)?.min(file.len());
+    copy(&mut image, 0, 0, headers_size)?;
+    for section in &sections {
+        let size = section.raw_size.min(section.mapped_size());
+        copy(
+            &mut image,
+            section.virtual_address,
+            section.raw_offset,
+            size,
+        )?;
+    }
+
+    let memory = MockMemory::new();
+    memory.set_pointer_width(if headers.is_64_bit { 8 } else { 4 });
+    // each section extends to the next one, so alignment padding keeps the protection of the section before it
+    let mut boundaries = vec![(0, Protection::READ)];
+    boundaries.extend(
+        sections
+            .iter()
+            .map(|section| (section.virtual_address, section.protection())),
+    );
+    for (index, &(start, protection)) in boundaries.iter().enumerate() {
+        let end = boundaries
+            .get(index + 1)
+            .map_or(size_of_image, |&(next, _)| next);
+        if start < end {
+            memory.add_region(image_base + start, image[start..end].to_vec(), protection)?;
+        }
+    }
+
+    Ok(Module {
+        name: name.to_string(),
+        handle: 0,
+        size: size_of_image,
+        base_address: image_base,
+        memory,
+    })
+}
+
+impl Module<MockMemory> {
+    /// Reads and maps a PE file from disk, see [`map_pe_file`].
+    pub fn from_pe_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::read(path)
+            .map_err(|error| anyhow!("failed to read {}: {error}", path.display()))?;
+        map_pe_file(
+            &path.file_name().unwrap_or_default().to_string_lossy(),
+            &file,
+        )
+    }
+}
+
+/// Reads a null terminated ASCII string of at most `max_length` bytes.
+///
+/// Chunks never cross a page boundary, so a name right before unreadable memory still reads.
+fn read_ascii<M: MemoryAccess>(memory: &M, address: usize, max_length: usize) -> Result<String> {
+    let mut bytes = vec![];
+    let mut chunk = [0u8; 32];
+    while bytes.len() < max_length {
+        let current = address
+            .checked_add(bytes.len())
+            .ok_or_else(|| anyhow!("string at {:#0x} overflows", address))?;
+        let length = chunk
+            .len()
+            .min(next_page(current) - current)
+            .min(max_length - bytes.len());
+        let chunk = &mut chunk[..length];
+        memory.read_bytes(current, chunk)?;
+        if let Some(end) = chunk.iter().position(|&byte| byte == 0) {
+            bytes.extend_from_slice(&chunk[..end]);
+            return Ok(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        bytes.extend_from_slice(chunk);
+    }
+    Err(anyhow!(
+        "string at {:#0x} is longer than {} bytes",
+        address,
+        max_length
+    ))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeExport {
+    Address(usize),
+    /// The export lives in another module, e.g. `NTDLL.RtlAllocateHeap`.
+    Forwarded(String),
+}
+
+/// Looks up an export by name in the export directory of the image at `base_address`.
+pub fn get_export<M: MemoryAccess>(
+    memory: &M,
+    base_address: usize,
+    name: &str,
+) -> Result<PeExport> {
+    let headers = PeHeaders::parse(memory, base_address)?;
+    let (directory_rva, directory_size) = headers.data_directory(memory, 0)?;
+    if directory_rva == 0 {
+        return Err(anyhow!("image at {:#0x} has no exports", base_address));
+    }
+
+    let directory = base_address + directory_rva as usize;
+    let number_of_names = memory.read::<u32>(directory + 0x18)? as usize;
+    let functions = base_address + memory.read::<u32>(directory + 0x1C)? as usize;
+    let names_rva = memory.read::<u32>(directory + 0x20)? as usize;
+    let names = base_address + names_rva;
+    let ordinals = base_address + memory.read::<u32>(directory + 0x24)? as usize;
+
+    // the count comes from target memory, the name table has to fit in the image
+    let size_of_image = headers.size_of_image(memory)?;
+    if number_of_names
+        .checked_mul(4)
+        .and_then(|size| names_rva.checked_add(size))
+        .is_none_or(|end| end > size_of_image)
+    {
+        return Err(anyhow!(
+            "export name table of the image at {:#0x} with {number_of_names} names exceeds the image",
+            base_address
+        ));
+    }
+    let mut name_rvas = vec![0u8; number_of_names * 4];
+    memory.read_bytes(names, &mut name_rvas)?;
+    for (index, name_rva) in name_rvas.chunks_exact(4).enumerate() {
+        let name_rva = u32::from_le_bytes(name_rva.try_into().unwrap()) as usize;
+        if read_ascii(memory, base_address + name_rva, 512)? != name {
+            continue;
+        }
+
+        let ordinal = memory.read::<u16>(ordinals + index * 2)? as usize;
+        let function_rva = memory.read::<u32>(functions + ordinal * 4)?;
+        let is_forwarded = function_rva >= directory_rva
+            && function_rva < directory_rva.saturating_add(directory_size);
+        if is_forwarded {
+            let forwarder = read_ascii(memory, base_address + function_rva as usize, 512)?;
+            return Ok(PeExport::Forwarded(forwarder));
+        }
+        return Ok(PeExport::Address(base_address + function_rva as usize));
+    }
+
+    Err(anyhow!(
+        "export {name} not found in image at {:#0x}",
+        base_address
+    ))
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Looks up an export of a PE module, works for internal and external modules alike.
+    pub fn get_export(&self, name: &str) -> Result<PeExport> {
+        get_export(&self.memory, self.base_address, name)
+    }
+
+    /// Reads the section table of a PE module.
+    pub fn sections(&self) -> Result<Vec<PeSection>> {
+        PeHeaders::parse(&self.memory, self.base_address)?.sections(&self.memory)
+    }
+
+    /// Finds runs of at least `min_length` `0xCC` or `0x00` bytes in the executable sections of a PE module.
+    pub fn find_code_caves(&self, min_length: usize) -> Result<Vec<CodeCave>> {
+        find_code_caves(&self.memory, self.base_address, min_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PE32+ file with a `.text` section of 0x100 bytes at RVA 0x1000.
+    fn pe_file(size_of_image: u32) -> Vec<u8> {
+        let mut file = vec![0u8; 0x400];
+        let mut put = |offset: usize, bytes: &[u8]| {
+            file[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        put(0x0, b"MZ");
+        put(0x3C, &0x40u32.to_le_bytes());
+        put(0x40, b"PE\0\0");
+        put(0x46, &1u16.to_le_bytes());
+        put(0x54, &0xF0u16.to_le_bytes());
+        put(0x58, &IMAGE_NT_OPTIONAL_HDR64_MAGIC.to_le_bytes());
+        put(0x70, &0x1_4000_0000u64.to_le_bytes());
+        put(0x78, &0x1000u32.to_le_bytes());
+        put(0x90, &size_of_image.to_le_bytes());
+        put(0x94, &0x200u32.to_le_bytes());
+        put(0x148, b".text");
+        put(0x150, &0x100u32.to_le_bytes());
+        put(0x154, &0x1000u32.to_le_bytes());
+        put(0x158, &0x200u32.to_le_bytes());
+        put(0x15C, &0x200u32.to_le_bytes());
+        put(
+            0x16C,
+            &(IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ).to_le_bytes(),
+        );
+        put(0x200, &[0x90; 0x10]);
+        put(0x210, &[0xCC; 0x10]);
+        put(0x220, &[0x90; 0x10]);
+        file
+    }
+
+    #[test]
+    fn maps_sections() {
+        let module = map_pe_file("game.exe", &pe_file(0x2000)).unwrap();
+        assert_eq!((module.base_address, module.size), (0x1_4000_0000, 0x2000));
+        assert_eq!(module.memory.pointer_width(), 8);
+        let text = module.memory.query_region(0x1_4000_1000).unwrap();
+        assert_eq!(text.protection, Protection::READ_EXECUTE);
+        assert_eq!(module.read::<u8>(0x1000).unwrap(), 0x90);
+        assert_eq!(module.sections().unwrap()[0].name, ".text");
+    }
+
+    #[test]
+    fn finds_code_caves() {
+        let module = map_pe_file("game.exe", &pe_file(0x2000)).unwrap();
+        let caves = module.find_code_caves(0x10).unwrap();
+        let found = caves
+            .iter()
+            .map(|cave| (cave.address - module.base_address, cave.len, cave.byte))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [(0x1010, 0x10, 0xCC), (0x1030, 0xFD0, 0x00)]);
+        assert!(caves
+            .iter()
+            .all(|cave| cave.protection == Protection::READ_EXECUTE && cave.section == ".text"));
+    }
+
+    #[test]
+    fn rejects_oversized_images() {
+        for size_of_image in [0x3000, u32::MAX] {
+            let error = map_pe_file("evil.exe", &pe_file(size_of_image)).unwrap_err();
+            assert!(error.to_string().contains("claims an image"), "{error}");
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_image_base() {
+        let mut file = pe_file(0x2000);
+        file[0x70..0x78].copy_from_slice(&(u64::MAX - 0xFFF).to_le_bytes());
+        let error = map_pe_file("evil.exe", &file).unwrap_err();
+        assert!(error.to_string().contains("overflows"), "{error}");
+    }
+
+    #[test]
+    fn reads_names_before_unreadable_memory() {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![b'a'; 0x1000], Protection::READ_WRITE)
+            .unwrap();
+        memory.write_bytes(0x1FF8, b"Export\0").unwrap();
+        assert_eq!(read_ascii(&memory, 0x1FF8, 512).unwrap(), "Export");
+        assert_eq!(
+            read_ascii(&memory, 0x1FD0, 512).unwrap(),
+            format!("{}Export", "a".repeat(0x28))
+        );
+        let error = read_ascii(&memory, 0x1000, 0x40).unwrap_err();
+        assert!(error.to_string().contains("longer than"), "{error}");
+        assert!(read_ascii(&memory, 0x1FF9, 4).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use crate::*;
+
+/// A multi-level pointer like `[[client.dll+0x1A2B]+0x10]+0x48`.
+///
+/// Resolving starts at the module base plus `base_offset`, then for every offset the current address is
+/// dereferenced and the offset added. The result is the final address, it is not dereferenced.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PointerChain {
+    /// Module the chain starts in, `None` makes `base_offset` an absolute address.
+    pub module: Option<String>,
+    pub base_offset: usize,
+    pub offsets: Vec<isize>,
+}
+
+/// Describes which hop of a [`PointerChain`] failed, reachable through [`Error::downcast_ref`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PointerChainError {
+    ModuleNotFound {
+        module: String,
+    },
+    /// Dereferencing `address` failed. `value` is the pointer read by the previous hop that led there.
+    ReadFailed {
+        hop: usize,
+        address: usize,
+        value: Option<usize>,
+        reason: String,
+    },
+    /// Dereferencing `address` returned a null pointer.
+    NullPointer {
+        hop: usize,
+        address: usize,
+    },
+}
+
+impl fmt::Display for PointerChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ModuleNotFound { module } => {
+                write!(f, "pointer chain module {module} not found")
+            }
+            Self::ReadFailed {
+                hop,
+                address,
+                value: Some(value),
+                reason,
+            } => write!(
+                f,
+                "pointer chain hop {hop} failed to read {address:#0x} (previous hop read {value:#0x}): {reason}"
+            ),
+            Self::ReadFailed {
+                hop,
+                address,
+                value: None,
+                reason,
+            } => write!(
+                f,
+                "pointer chain hop {hop} failed to read {address:#0x}: {reason}"
+            ),
+            Self::NullPointer { hop, address } => {
+                write!(f, "pointer chain hop {hop} read a null pointer at {address:#0x}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PointerChainError {}
+
+impl PointerChain {
+    pub fn new(module: &str, base_offset: usize, offsets: &[isize]) -> Self {
+        Self {
+            module: Some(module.to_string()),
+            base_offset,
+            offsets: offsets.to_vec(),
+        }
+    }
+
+    pub fn from_address(address: usize, offsets: &[isize]) -> Self {
+        Self {
+            module: None,
+            base_offset: address,
+            offsets: offsets.to_vec(),
+        }
+    }
+
+    /// Resolves the chain with the module, if any, located at `module_base`.
+    pub fn resolve_from<M: MemoryAccess>(&self, memory: &M, module_base: usize) -> Result<usize> {
+        let mut address = module_base.wrapping_add(self.base_offset);
+        let mut value = None;
+        for (hop, &offset) in self.offsets.iter().enumerate() {
+            let pointer =
+                memory
+                    .read_pointer(address)
+                    .map_err(|error| PointerChainError::ReadFailed {
+                        hop,
+                        address,
+                        value,
+                        reason: error.to_string(),
+                    })?;
+            if pointer == 0 {
+                return Err(PointerChainError::NullPointer { hop, address }.into());
+            }
+            value = Some(pointer);
+            address = pointer.wrapping_add_signed(offset);
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Display for PointerChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut expression = match &self.module {
+            Some(module) => format!("{module}+{:#X}", self.base_offset),
+            None => format!("{:#X}", self.base_offset),
+        };
+        for &offset in self.offsets.iter() {
+            let sign = if offset < 0 { '-' } else { '+' };
+            expression = format!("[{expression}]{sign}{:#X}", offset.unsigned_abs());
+        }
+        f.write_str(&expression)
+    }
+}
+
+impl<M: MemoryAccess + Clone> Process<M> {
+    pub fn resolve(&self, chain: &PointerChain) -> Result<usize> {
+        let module_base = match &chain.module {
+            Some(module) => {
+                self.get_module_by_name(module)
+                    .map_err(|_| PointerChainError::ModuleNotFound {
+                        module: module.clone(),
+                    })?
+                    .base_address
+            }
+            None => 0,
+        };
+        chain.resolve_from(&self.memory, module_base)
+    }
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Resolves a chain that starts in this module, or at an absolute address.
+    pub fn resolve(&self, chain: &PointerChain) -> Result<usize> {
+        let module_base = match &chain.module {
+            Some(module) if *module == self.name => self.base_address,
+            Some(module) => {
+                return Err(PointerChainError::ModuleNotFound {
+                    module: module.clone(),
+                }
+                .into())
+            }
+            None => 0,
+        };
+        chain.resolve_from(&self.memory, module_base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `game.exe+0x100` points to an object at 0x2000, whose pointer at +0x10 leads to 0x3000.
+    fn process() -> MockProcess {
+        let mut process = MockProcess::new(1);
+        let mut module = vec![0u8; 0x1000];
+        module[0x100..0x108].copy_from_slice(&0x2000u64.to_le_bytes());
+        process
+            .add_module("game.exe", 0x1000, module, Protection::READ_WRITE)
+            .unwrap();
+        let mut object = vec![0u8; 0x1000];
+        object[0x10..0x18].copy_from_slice(&0x3000u64.to_le_bytes());
+        process
+            .add_region(0x2000, object, Protection::READ_WRITE)
+            .unwrap();
+        process
+    }
+
+    fn chain_error(result: Result<usize>) -> PointerChainError {
+        result
+            .unwrap_err()
+            .downcast_ref::<PointerChainError>()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn resolves_offsets() {
+        let process = process();
+        let chain = PointerChain::new("game.exe", 0x100, &[0x10, 0x48]);
+        assert_eq!(process.resolve(&chain).unwrap(), 0x3048);
+        assert_eq!(
+            process.modules[0].resolve(&chain).unwrap(),
+            0x3048,
+            "modules resolve their own chains"
+        );
+        let negative = PointerChain::from_address(0x1100, &[-0x10]);
+        assert_eq!(process.resolve(&negative).unwrap(), 0x1FF0);
+        assert_eq!(chain.to_string(), "[[game.exe+0x100]+0x10]+0x48");
+        assert_eq!(negative.to_string(), "[0x1100]-0x10");
+    }
+
+    #[test]
+    fn respects_pointer_width() {
+        let process = process();
+        process.memory.set_pointer_width(4);
+        process.memory.write(0x1104, 0xFFFF_FFFFu32).unwrap();
+        let chain = PointerChain::new("game.exe", 0x100, &[0]);
+        assert_eq!(process.resolve(&chain).unwrap(), 0x2000);
+    }
+
+    #[test]
+    fn reports_failing_hop() {
+        let process = process();
+        assert_eq!(
+            chain_error(process.resolve(&PointerChain::new("other.dll", 0, &[]))),
+            PointerChainError::ModuleNotFound {
+                module: "other.dll".into()
+            }
+        );
+        assert_eq!(
+            chain_error(process.resolve(&PointerChain::new("game.exe", 0x100, &[0x20, 0]))),
+            PointerChainError::NullPointer {
+                hop: 1,
+                address: 0x2020
+            }
+        );
+        match chain_error(process.resolve(&PointerChain::new("game.exe", 0x100, &[0x10, 0, 0]))) {
+            PointerChainError::ReadFailed {
+                hop,
+                address,
+                value,
+                ..
+            } => assert_eq!((hop, address, value), (2, 0x3000, Some(0x3000))),
+            error => panic!("unexpected error {error:?}"),
+        }
+    }
+}
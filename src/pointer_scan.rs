@@ -0,0 +1,568 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use crate::{
+    scanner::{is_scannable, BlockData, BLOCK_SIZE},
+    *,
+};
+
+const POINTER_MAP_MAGIC: &[u8; 4] = b"CLPM";
+const POINTER_MAP_VERSION: u32 = 1;
+/// Longest module name accepted when loading a map.
+const MAX_MODULE_NAME_LENGTH: usize = 0x1000;
+
+/// Module known to a [`PointerMap`], addresses inside it are static.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointerMapModule {
+    pub name: String,
+    pub base_address: usize,
+    pub size: usize,
+}
+
+/// Limits of [`PointerMap::find_paths`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointerScanOptions {
+    /// Most offsets in a path.
+    pub max_depth: usize,
+    /// Largest offset added after dereferencing, offsets are never negative.
+    pub max_offset: usize,
+    /// The search stops once this many paths were found.
+    pub max_results: usize,
+}
+
+impl Default for PointerScanOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 5,
+            max_offset: 0x1000,
+            max_results: 100_000,
+        }
+    }
+}
+
+/// Every aligned value in readable memory that points into readable memory, indexed both ways.
+///
+/// Building a map is the slow part of a pointer scan, searching it does not touch the target. Maps can be saved,
+/// so paths found in one run can be checked against a map of another run with [`PointerMap::filter_paths`].
+///
+/// Every pointer costs 12 bytes of RAM in maps of 32-bit targets and 20 bytes in maps of 64-bit targets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PointerMap {
+    pointer_width: usize,
+    modules: Vec<PointerMapModule>,
+    entries: Entries,
+    /// Indices into `entries` sorted by value.
+    by_value: Vec<u32>,
+}
+
+impl Default for PointerMap {
+    fn default() -> Self {
+        Self {
+            pointer_width: mem::size_of::<usize>(),
+            modules: vec![],
+            entries: Entries::Wide(vec![]),
+            by_value: vec![],
+        }
+    }
+}
+
+/// `(location, value)` pairs sorted by location, stored as `u32` for 4 byte pointers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entries {
+    Narrow(Vec<(u32, u32)>),
+    Wide(Vec<(usize, usize)>),
+}
+
+impl Entries {
+    fn new(pointer_width: usize) -> Result<Self> {
+        match pointer_width {
+            4 => Ok(Self::Narrow(vec![])),
+            8 => Ok(Self::Wide(vec![])),
+            _ => Err(anyhow!("pointer width {pointer_width} is not 4 or 8")),
+        }
+    }
+
+    fn push(&mut self, location: usize, value: usize) -> Result<()> {
+        match self {
+            Self::Narrow(entries) => {
+                let (Ok(location), Ok(value)) = (u32::try_from(location), u32::try_from(value))
+                else {
+                    return Err(anyhow!(
+                        "pointer {value:#0x} at {location:#0x} does not fit a 32-bit map"
+                    ));
+                };
+                entries.push((location, value));
+            }
+            Self::Wide(entries) => entries.push((location, value)),
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        match self {
+            Self::Narrow(entries) => entries.len(),
+            Self::Wide(entries) => entries.len(),
+        }
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> (usize, usize) {
+        match self {
+            Self::Narrow(entries) => (entries[index].0 as usize, entries[index].1 as usize),
+            Self::Wide(entries) => entries[index],
+        }
+    }
+
+    fn sort(&mut self) {
+        match self {
+            Self::Narrow(entries) => entries.sort_unstable(),
+            Self::Wide(entries) => entries.sort_unstable(),
+        }
+    }
+
+    /// Index of the entry at `location`.
+    fn find(&self, location: usize) -> Option<usize> {
+        match self {
+            Self::Narrow(entries) => {
+                let location = u32::try_from(location).ok()?;
+                entries
+                    .binary_search_by_key(&location, |&(location, _)| location)
+                    .ok()
+            }
+            Self::Wide(entries) => entries
+                .binary_search_by_key(&location, |&(location, _)| location)
+                .ok(),
+        }
+    }
+}
+
+impl PointerMap {
+    /// Reads every readable region of the process, with its modules as static bases.
+    pub fn build<M: MemoryAccess>(process: &Process<M>) -> Result<Self> {
+        let pointer_width = process.memory.pointer_width();
+        let regions = process
+            .memory
+            .regions()?
+            .into_iter()
+            .filter(is_scannable)
+            .collect::<Vec<_>>();
+        let is_readable = |value: usize| {
+            let index = regions.partition_point(|region| region.end_address() <= value);
+            regions
+                .get(index)
+                .is_some_and(|region| region.contains(value))
+        };
+
+        let mut entries = Entries::new(pointer_width)?;
+        for region in &regions {
+            let mut address = region.base_address.next_multiple_of(pointer_width);
+            while address + pointer_width <= region.end_address() {
+                let length = (region.end_address() - address).min(BLOCK_SIZE);
+                let length = length - length % pointer_width;
+                let block = BlockData::read(&process.memory, &regions, address, length)?;
+                for offset in (0..length).step_by(pointer_width) {
+                    if !block.is_valid(offset..offset + pointer_width) {
+                        continue;
+                    }
+                    let mut bytes = [0u8; 8];
+                    bytes[..pointer_width]
+                        .copy_from_slice(&block.data[offset..offset + pointer_width]);
+                    let value = u64::from_le_bytes(bytes) as usize;
+                    if value != 0 && is_readable(value) {
+                        entries.push(address + offset, value)?;
+                    }
+                }
+                address += length;
+            }
+        }
+
+        let modules = process
+            .modules
+            .iter()
+            .map(|module| PointerMapModule {
+                name: module.name.clone(),
+                base_address: module.base_address,
+                size: module.size,
+            })
+            .collect();
+        Self::from_parts(pointer_width, modules, entries)
+    }
+
+    fn from_parts(
+        pointer_width: usize,
+        modules: Vec<PointerMapModule>,
+        mut entries: Entries,
+    ) -> Result<Self> {
+        if entries.len() > u32::MAX as usize {
+            return Err(anyhow!(
+                "pointer map with {} entries is too large",
+                entries.len()
+            ));
+        }
+        entries.sort();
+        let mut by_value = (0..entries.len() as u32).collect::<Vec<_>>();
+        by_value.sort_unstable_by_key(|&index| entries.get(index as usize).1);
+        Ok(Self {
+            pointer_width,
+            modules,
+            entries,
+            by_value,
+        })
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.len() == 0
+    }
+
+    #[inline]
+    pub fn pointer_width(&self) -> usize {
+        self.pointer_width
+    }
+
+    #[inline]
+    pub fn modules(&self) -> &[PointerMapModule] {
+        &self.modules
+    }
+
+    /// The pointer stored at `address` when the map was built.
+    pub fn read_pointer(&self, address: usize) -> Option<usize> {
+        self.entries
+            .find(address)
+            .map(|index| self.entries.get(index).1)
+    }
+
+    /// Every `(location, value)` whose value lies in `values`, ordered by value.
+    pub fn pointers_to(
+        &self,
+        values: RangeInclusive<usize>,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let start = self
+            .by_value
+            .partition_point(|&index| self.entries.get(index as usize).1 < *values.start());
+        self.by_value[start..]
+            .iter()
+            .map(|&index| self.entries.get(index as usize))
+            .take_while(move |&(_, value)| value <= *values.end())
+    }
+
+    /// The module and offset of `address` if it lies inside a module.
+    pub fn static_base(&self, address: usize) -> Option<(&str, usize)> {
+        self.modules
+            .iter()
+            .find(|module| {
+                address >= module.base_address && address - module.base_address < module.size
+            })
+            .map(|module| (module.name.as_str(), address - module.base_address))
+    }
+
+    /// Finds pointer paths from a module to `target`, shortest paths are found first at each level.
+    ///
+    /// A path ends at the first static location reached.
+    pub fn find_paths(&self, target: usize, options: &PointerScanOptions) -> Vec<PointerChain> {
+        let mut search = PathSearch {
+            map: self,
+            options,
+            offsets: vec![],
+            results: vec![],
+            dead_ends: HashSet::new(),
+        };
+        search.search(target);
+        search.results
+    }
+
+    /// Follows `chain` through the pointers recorded in the map.
+    pub fn resolve(&self, chain: &PointerChain) -> Option<usize> {
+        let module_base = match &chain.module {
+            Some(name) => {
+                self.modules
+                    .iter()
+                    .find(|module| module.name == *name)?
+                    .base_address
+            }
+            None => 0,
+        };
+        let mut address = module_base.wrapping_add(chain.base_offset);
+        for &offset in &chain.offsets {
+            address = self.read_pointer(address)?.wrapping_add_signed(offset);
+        }
+        Some(address)
+    }
+
+    /// Keeps the paths that also lead to `target` in this map, use it with a map of another run to drop unstable
+    /// paths.
+    pub fn filter_paths(&self, paths: &[PointerChain], target: usize) -> Vec<PointerChain> {
+        paths
+            .iter()
+            .filter(|path| self.resolve(path) == Some(target))
+            .cloned()
+            .collect()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|error| anyhow!("failed to create {}: {error}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(POINTER_MAP_MAGIC)?;
+        writer.write_all(&POINTER_MAP_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.pointer_width as u32).to_le_bytes())?;
+        writer.write_all(&(self.modules.len() as u64).to_le_bytes())?;
+        for module in &self.modules {
+            writer.write_all(&(module.name.len() as u64).to_le_bytes())?;
+            writer.write_all(module.name.as_bytes())?;
+            writer.write_all(&(module.base_address as u64).to_le_bytes())?;
+            writer.write_all(&(module.size as u64).to_le_bytes())?;
+        }
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (location, value) in (0..self.entries.len()).map(|index| self.entries.get(index)) {
+            writer.write_all(&(location as u64).to_le_bytes())?;
+            writer.write_all(&(value as u64).to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|error| anyhow!("failed to open {}: {error}", path.display()))?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != POINTER_MAP_MAGIC {
+            return Err(anyhow!("{} is not a pointer map", path.display()));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != POINTER_MAP_VERSION {
+            return Err(anyhow!(
+                "{} has pointer map version {version}, expected {POINTER_MAP_VERSION}",
+                path.display()
+            ));
+        }
+        let pointer_width = read_u32(&mut reader)? as usize;
+        let mut entries = Entries::new(pointer_width)
+            .map_err(|error| anyhow!("{} is corrupt: {error}", path.display()))?;
+
+        let module_count = read_u64(&mut reader)? as usize;
+        let mut modules = Vec::with_capacity(module_count.min(0x1000));
+        for _ in 0..module_count {
+            let name_length = read_u64(&mut reader)?;
+            if name_length > MAX_MODULE_NAME_LENGTH as u64 {
+                return Err(anyhow!(
+                    "{} is corrupt: module name of {name_length} bytes",
+                    path.display()
+                ));
+            }
+            let mut name = vec![0u8; name_length as usize];
+            reader.read_exact(&mut name)?;
+            modules.push(PointerMapModule {
+                name: String::from_utf8(name)
+                    .map_err(|_| anyhow!("{} has an invalid module name", path.display()))?,
+                base_address: read_u64(&mut reader)? as usize,
+                size: read_u64(&mut reader)? as usize,
+            });
+        }
+
+        let entry_count = read_u64(&mut reader)?;
+        for _ in 0..entry_count {
+            let location = read_u64(&mut reader)? as usize;
+            let value = read_u64(&mut reader)? as usize;
+            entries
+                .push(location, value)
+                .map_err(|error| anyhow!("{} is corrupt: {error}", path.display()))?;
+        }
+        Self::from_parts(pointer_width, modules, entries)
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Depth first search backwards from the target, one offset per level.
+struct PathSearch<'a> {
+    map: &'a PointerMap,
+    options: &'a PointerScanOptions,
+    /// Offsets from the target back to the current address, in reverse order.
+    offsets: Vec<isize>,
+    results: Vec<PointerChain>,
+    /// `(address, remaining depth)` pairs that led to no path.
+    dead_ends: HashSet<(usize, usize)>,
+}
+
+impl PathSearch<'_> {
+    /// Collects paths to `address`, returns whether any was found.
+    fn search(&mut self, address: usize) -> bool {
+        let remaining = self.options.max_depth - self.offsets.len();
+        if remaining == 0 || self.dead_ends.contains(&(address, remaining)) {
+            return false;
+        }
+
+        let map = self.map;
+        let mut found = false;
+        let mut deeper = vec![];
+        for (location, value) in
+            map.pointers_to(address.saturating_sub(self.options.max_offset)..=address)
+        {
+            if self.results.len() >= self.options.max_results {
+                return true;
+            }
+            let offset = (address - value) as isize;
+            if let Some((module, base_offset)) = map.static_base(location) {
+                let offsets = std::iter::once(offset)
+                    .chain(self.offsets.iter().rev().copied())
+                    .collect::<Vec<_>>();
+                self.results
+                    .push(PointerChain::new(module, base_offset, &offsets));
+                found = true;
+            } else {
+                deeper.push((location, offset));
+            }
+        }
+
+        // static paths at this level come before longer ones
+        for (location, offset) in deeper {
+            if self.results.len() >= self.options.max_results {
+                return true;
+            }
+            self.offsets.push(offset);
+            found |= self.search(location);
+            self.offsets.pop();
+        }
+
+        if !found {
+            self.dead_ends.insert((address, remaining));
+        }
+        found
+    }
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Builds a [`PointerMap`] of this process.
+    pub fn pointer_map(&self) -> Result<PointerMap> {
+        PointerMap::build(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cheatlib-test-{}-{name}.clpm", std::process::id()))
+    }
+
+    /// 32-bit process where `[game.exe+0x10]+0x8` points 0x40 bytes before `heap`.
+    fn process(heap: u32) -> MockProcess {
+        let mut process = MockProcess::new(1);
+        process.memory.set_pointer_width(4);
+        let mut module = vec![0u8; 0x1000];
+        module[0x10..0x14].copy_from_slice(&0x10000u32.to_le_bytes());
+        process
+            .add_module("game.exe", 0x40_0000, module, Protection::READ_WRITE)
+            .unwrap();
+        let mut data = vec![0u8; 0x1000];
+        data[0x8..0xC].copy_from_slice(&heap.to_le_bytes());
+        process
+            .add_region(0x10000, data, Protection::READ_WRITE)
+            .unwrap();
+        process
+            .add_region(0x20000, vec![0u8; 0x2000], Protection::READ_WRITE)
+            .unwrap();
+        process
+    }
+
+    #[test]
+    fn finds_static_paths() {
+        let map = process(0x20000).pointer_map().unwrap();
+        assert_eq!(map.pointer_width(), 4);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.read_pointer(0x40_0010), Some(0x10000));
+        assert_eq!(map.read_pointer(0x40_0014), None);
+        assert_eq!(map.static_base(0x40_0010), Some(("game.exe", 0x10)));
+
+        let chain = PointerChain::new("game.exe", 0x10, &[0x8, 0x40]);
+        let paths = map.find_paths(0x20040, &PointerScanOptions::default());
+        assert_eq!(paths, std::slice::from_ref(&chain));
+        assert_eq!(map.resolve(&chain), Some(0x20040));
+
+        let options = PointerScanOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(map.find_paths(0x20040, &options).is_empty());
+        let options = PointerScanOptions {
+            max_offset: 0x20,
+            ..Default::default()
+        };
+        assert!(map.find_paths(0x20040, &options).is_empty());
+    }
+
+    #[test]
+    fn save_load_and_filter() {
+        let map = process(0x20000).pointer_map().unwrap();
+        let path = temp_path("round-trip");
+        map.save(&path).unwrap();
+        let loaded = PointerMap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), map);
+
+        let paths = map.find_paths(0x20040, &PointerScanOptions::default());
+        let moved = process(0x21000).pointer_map().unwrap();
+        assert!(moved.filter_paths(&paths, 0x20040).is_empty());
+        assert_eq!(moved.filter_paths(&paths, 0x21040), paths);
+    }
+
+    #[test]
+    fn load_rejects_corrupt_maps() {
+        let header = |pointer_width: u32| {
+            let mut bytes = POINTER_MAP_MAGIC.to_vec();
+            bytes.extend_from_slice(&POINTER_MAP_VERSION.to_le_bytes());
+            bytes.extend_from_slice(&pointer_width.to_le_bytes());
+            bytes
+        };
+        let mut huge_name = header(8);
+        huge_name.extend_from_slice(&1u64.to_le_bytes());
+        huge_name.extend_from_slice(&u64::MAX.to_le_bytes());
+        let mut wide_entry = header(4);
+        wide_entry.extend_from_slice(&0u64.to_le_bytes());
+        wide_entry.extend_from_slice(&1u64.to_le_bytes());
+        wide_entry.extend_from_slice(&0x1_0000_0000u64.to_le_bytes());
+        wide_entry.extend_from_slice(&0x1000u64.to_le_bytes());
+
+        let path = temp_path("corrupt");
+        for (bytes, message) in [
+            (header(3), "pointer width 3"),
+            (huge_name, "module name"),
+            (wide_entry, "does not fit"),
+        ] {
+            std::fs::write(&path, bytes).unwrap();
+            let error = PointerMap::load(&path).unwrap_err().to_string();
+            assert!(error.contains(message), "{error}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,44 +1,162 @@
-use module::Module;
-
-#[cfg(windows)]
+#[cfg(all(windows, feature = "external"))]
 use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_ALL_ACCESS};
 
 use crate::*;
 
-pub struct Process {
+pub struct Process<M> {
     pub id: u32,
-    pub handle: HANDLE,
-    pub modules: Vec<Module>,
+    pub memory: M,
+    pub modules: Vec<Module<M>>,
+}
+
+impl<M: Clone> Process<M> {
+    pub fn get_module_by_name(&self, module_name: &str) -> Result<Module<M>> {
+        for module in self.modules.iter() {
+            if module.name == module_name {
+                return Ok(module.clone());
+            }
+        }
+        Err(anyhow!(
+            "no module with name {module_name} found in process"
+        ))
+    }
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Iterates every region that is not free, filter with [`MemoryRegion::is_readable`] and friends.
+    ///
+    /// Same as [`MemoryAccess::regions`] on the process, which collects them into a `Vec`.
+    pub fn iter_regions(&self) -> Result<impl Iterator<Item = MemoryRegion>> {
+        Ok(self.memory.regions()?.into_iter())
+    }
 }
 
-#[cfg(windows)]
-impl Process {
-    pub fn from_name(name: &str) -> Result<Process> {
+impl<M: MemoryAccess> MemoryAccess for Process<M> {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        self.memory.read_bytes(address, buffer)
+    }
+
+    fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+        self.memory.write_bytes(address, data)
+    }
+
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        self.memory.query_region(address)
+    }
+
+    fn protect(&self, address: usize, size: usize, protection: Protection) -> Result<Protection> {
+        self.memory.protect(address, size, protection)
+    }
+
+    fn protect_raw(&self, address: usize, size: usize, protection: Protection) -> Result<u32> {
+        self.memory.protect_raw(address, size, protection)
+    }
+
+    fn restore_protection(&self, address: usize, size: usize, raw: u32) -> Result<()> {
+        self.memory.restore_protection(address, size, raw)
+    }
+
+    fn ignores_protection(&self) -> bool {
+        self.memory.ignores_protection()
+    }
+
+    fn allocate(
+        &self,
+        size: usize,
+        protection: Protection,
+        preferred: Option<usize>,
+    ) -> Result<usize> {
+        self.memory.allocate(size, protection, preferred)
+    }
+
+    fn free(&self, address: usize, size: usize) -> Result<()> {
+        self.memory.free(address, size)
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        self.memory.regions()
+    }
+
+    fn read_partial(&self, address: usize, len: usize) -> Result<PartialRead> {
+        self.memory.read_partial(address, len)
+    }
+
+    fn read_scatter(&self, reads: &mut [(usize, &mut [u8])]) -> Vec<bool> {
+        self.memory.read_scatter(reads)
+    }
+
+    fn pointer_width(&self) -> usize {
+        self.memory.pointer_width()
+    }
+}
+
+#[cfg(all(windows, feature = "external"))]
+impl Process<RemoteMemory> {
+    pub fn from_name(name: &str) -> Result<Self> {
         let Some(entry) = get_process_entry_by_name(name) else {
             return Err(anyhow!("process {name} not found"));
         };
         let handle = unsafe { OpenProcess(PROCESS_ALL_ACCESS, 0, entry.th32ProcessID) };
+        if handle == 0 {
+            return Err(anyhow!("failed to open process {name}"));
+        }
+        let memory = RemoteMemory::new(handle);
         Ok(Self {
             id: entry.th32ProcessID,
-            handle,
-            modules: get_process_modules((handle, entry.th32ProcessID)),
+            modules: get_process_modules(&memory, entry.th32ProcessID),
+            memory,
         })
     }
+}
 
-    pub fn get_module_by_name(&self, module_name: &str) -> Result<Module> {
-        for module in self.modules.iter() {
-            if module.name == module_name {
-                return Ok(module.clone());
-            }
+#[cfg(all(target_os = "linux", feature = "external"))]
+impl Process<RemoteMemory> {
+    pub fn from_name(name: &str) -> Result<Self> {
+        let Some(process_id) = get_process_id_by_name(name) else {
+            return Err(anyhow!("process {name} not found"));
+        };
+        let memory = RemoteMemory::new(process_id);
+        Ok(Self {
+            id: process_id,
+            modules: get_process_modules(&memory, process_id),
+            memory,
+        })
+    }
+}
+
+#[cfg(all(any(windows, target_os = "linux"), feature = "internal"))]
+impl Process<LocalMemory> {
+    /// Returns the process the library is loaded into.
+    pub fn current() -> Self {
+        let id = std::process::id();
+        Self {
+            id,
+            modules: get_process_modules(&LocalMemory, id),
+            memory: LocalMemory,
         }
-        Err(anyhow!(
-            "no module with name {module_name} found in process"
-        ))
     }
 }
 
-impl Drop for Process {
-    fn drop(&mut self) {
-        close_handle(self.handle);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iterates_regions() {
+        let mut process = MockProcess::new(1);
+        process
+            .add_region(0x1000, vec![0u8; 0x1000], Protection::READ)
+            .unwrap();
+        process
+            .add_region(0x3000, vec![0u8; 0x2000], Protection::READ_WRITE)
+            .unwrap();
+        let writable = process
+            .iter_regions()
+            .unwrap()
+            .filter(MemoryRegion::is_writable)
+            .map(|region| (region.base_address, region.size))
+            .collect::<Vec<_>>();
+        assert_eq!(writable, [(0x3000, 0x2000)]);
+        assert_eq!(process.regions().unwrap().len(), 2);
     }
 }
@@ -0,0 +1,205 @@
+use crate::{memory::next_page, *};
+
+/// Changes the protection of an exact byte range and restores the original protections when dropped.
+///
+/// The original protection is recorded per region as raw backend flags, so a range spanning pages with different
+/// protections is restored page for page, guard pages included, even when the guarded operation returns early with
+/// an error.
+#[must_use = "the original protection is restored as soon as the guard is dropped"]
+pub struct ProtectGuard<'a, M: MemoryAccess + ?Sized> {
+    memory: &'a M,
+    /// Page-aligned `(address, size, original raw protection)` spans that were changed.
+    changed: Vec<(usize, usize, u32)>,
+}
+
+impl<'a, M: MemoryAccess + ?Sized> ProtectGuard<'a, M> {
+    /// Records the protections of the pages spanning `address..address + size`.
+    fn spans(memory: &M, address: usize, size: usize) -> Result<Vec<(usize, usize, Protection)>> {
+        let end = address
+            .checked_add(size.max(1))
+            .ok_or_else(|| anyhow!("protection range at {:#0x} overflows", address))?;
+        let end = next_page(end - 1);
+
+        let mut spans = vec![];
+        let mut current = address & !(PAGE_SIZE - 1);
+        while current < end {
+            let region = memory.query_region(current)?;
+            if !region.is_committed() {
+                return Err(anyhow!(
+                    "cannot change the protection of uncommitted memory at {:#0x}",
+                    current
+                ));
+            }
+            let span_end = region.end_address().min(end);
+            if span_end <= current {
+                return Err(anyhow!("invalid region returned for {:#0x}", current));
+            }
+            spans.push((current, span_end - current, region.protection));
+            current = span_end;
+        }
+        Ok(spans)
+    }
+
+    /// Sets `protection` on every page spanning `address..address + size`.
+    pub fn new(memory: &'a M, address: usize, size: usize, protection: Protection) -> Result<Self> {
+        let mut guard = Self {
+            memory,
+            changed: vec![],
+        };
+        for (span_address, span_size, _) in Self::spans(memory, address, size)? {
+            let original = memory.protect_raw(span_address, span_size, protection)?;
+            guard.changed.push((span_address, span_size, original));
+        }
+        Ok(guard)
+    }
+
+    /// Adds `required` to the pages spanning `address..address + size` that lack it, keeping their other flags.
+    ///
+    /// Does not touch anything when every page already allows the access, or when the backend
+    /// [ignores protection](MemoryAccess::ignores_protection).
+    pub fn ensure(
+        memory: &'a M,
+        address: usize,
+        size: usize,
+        required: Protection,
+    ) -> Result<Self> {
+        let mut guard = Self {
+            memory,
+            changed: vec![],
+        };
+        if memory.ignores_protection() {
+            return Ok(guard);
+        }
+        for (span_address, span_size, current) in Self::spans(memory, address, size)? {
+            if current.contains(required) {
+                continue;
+            }
+            let original = memory.protect_raw(span_address, span_size, current | required)?;
+            guard.changed.push((span_address, span_size, original));
+        }
+        Ok(guard)
+    }
+
+    /// Whether any protection was changed.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        !self.changed.is_empty()
+    }
+}
+
+impl<M: MemoryAccess + ?Sized> Drop for ProtectGuard<'_, M> {
+    fn drop(&mut self) {
+        for &(address, size, original) in self.changed.iter().rev() {
+            let _ = self.memory.restore_protection(address, size, original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three adjacent pages with different protections.
+    fn memory() -> MockMemory {
+        let memory = MockMemory::new();
+        for (address, protection) in [
+            (0x1000, Protection::READ),
+            (0x2000, Protection::READ_WRITE),
+            (0x3000, Protection::READ_EXECUTE),
+        ] {
+            memory
+                .add_region(address, vec![0u8; 0x1000], protection)
+                .unwrap();
+        }
+        memory
+    }
+
+    fn protections<M: MemoryAccess>(memory: &M) -> Vec<Protection> {
+        [0x1000, 0x2000, 0x3000]
+            .map(|address| memory.query_region(address).unwrap().protection)
+            .to_vec()
+    }
+
+    const ORIGINAL: [Protection; 3] = [
+        Protection::READ,
+        Protection::READ_WRITE,
+        Protection::READ_EXECUTE,
+    ];
+
+    #[test]
+    fn restores_every_region() {
+        let memory = memory();
+        let guard = ProtectGuard::new(&memory, 0x1FF0, 0x1020, Protection::READ_WRITE).unwrap();
+        assert!(guard.is_active());
+        assert_eq!(protections(&memory), [Protection::READ_WRITE; 3]);
+        memory.write(0x3008, 1u64).unwrap();
+        drop(guard);
+        assert_eq!(protections(&memory), ORIGINAL);
+    }
+
+    #[test]
+    fn ensure_adds_only_what_is_missing() {
+        let memory = memory();
+        let guard = ProtectGuard::ensure(&memory, 0x1000, 0x3000, Protection::WRITE).unwrap();
+        assert_eq!(
+            protections(&memory),
+            [
+                Protection::READ_WRITE,
+                Protection::READ_WRITE,
+                Protection::READ_WRITE_EXECUTE
+            ]
+        );
+        assert_eq!(guard.changed.len(), 2, "the writable page is left alone");
+        drop(guard);
+        assert_eq!(protections(&memory), ORIGINAL);
+
+        let guard = ProtectGuard::ensure(&memory, 0x2000, 0x10, Protection::WRITE).unwrap();
+        assert!(!guard.is_active());
+        assert!(ProtectGuard::ensure(&memory, 0x3FF0, 0x20, Protection::WRITE).is_err());
+        assert_eq!(protections(&memory), ORIGINAL);
+    }
+
+    /// Delegates to [`MockMemory`] but refuses to change the protection at `fail_at`.
+    struct FailingProtect {
+        inner: MockMemory,
+        fail_at: usize,
+    }
+
+    impl MemoryAccess for FailingProtect {
+        fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+            self.inner.read_bytes(address, buffer)
+        }
+
+        fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+            self.inner.write_bytes(address, data)
+        }
+
+        fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+            self.inner.query_region(address)
+        }
+
+        fn protect(
+            &self,
+            address: usize,
+            size: usize,
+            protection: Protection,
+        ) -> Result<Protection> {
+            if address == self.fail_at {
+                return Err(anyhow!("protection of {:#0x} is locked", address));
+            }
+            self.inner.protect(address, size, protection)
+        }
+    }
+
+    #[test]
+    fn rolls_back_when_a_later_span_fails() {
+        let memory = FailingProtect {
+            inner: memory(),
+            fail_at: 0x3000,
+        };
+        assert!(ProtectGuard::new(&memory, 0x1000, 0x3000, Protection::READ_WRITE).is_err());
+        assert_eq!(protections(&memory), ORIGINAL);
+        assert!(ProtectGuard::ensure(&memory, 0x1000, 0x3000, Protection::WRITE).is_err());
+        assert_eq!(protections(&memory), ORIGINAL);
+    }
+}
@@ -0,0 +1,292 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::*;
+
+static GLOBAL: Mutex<Registry> = Mutex::new(Registry::new());
+
+/// Something that can be switched on and off, like a [`Patch`] or a minhook hook.
+pub trait Toggle: Send {
+    fn enable(&mut self) -> Result<()>;
+
+    fn disable(&mut self) -> Result<()>;
+
+    fn is_enabled(&self) -> bool;
+}
+
+impl<M: MemoryAccess + Send> Toggle for Patch<M> {
+    fn enable(&mut self) -> Result<()> {
+        Patch::enable(self)
+    }
+
+    fn disable(&mut self) -> Result<()> {
+        Patch::disable(self)
+    }
+
+    fn is_enabled(&self) -> bool {
+        Patch::is_enabled(self)
+    }
+}
+
+/// Listing entry returned by [`Registry::list`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegistryEntry {
+    pub name: String,
+    pub group: String,
+    pub enabled: bool,
+}
+
+struct Entry {
+    name: String,
+    group: String,
+    item: Box<dyn Toggle>,
+}
+
+/// Named collection of patches and hooks, torn down in reverse registration order.
+///
+/// The process-wide instance returned by [`global`] is torn down by `dll_main!` when the DLL is unloaded.
+#[derive(Default)]
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+impl Registry {
+    pub const fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Takes ownership of `item` under `name`, names must be unique.
+    pub fn register(&mut self, name: &str, group: &str, item: impl Toggle + 'static) -> Result<()> {
+        if self.contains(name) {
+            return Err(anyhow!("{name} is already registered"));
+        }
+        self.entries.push(Entry {
+            name: name.to_string(),
+            group: group.to_string(),
+            item: Box::new(item),
+        });
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.name == name)
+    }
+
+    /// Lists every entry in registration order.
+    pub fn list(&self) -> Vec<RegistryEntry> {
+        self.entries
+            .iter()
+            .map(|entry| RegistryEntry {
+                name: entry.name.clone(),
+                group: entry.group.clone(),
+                enabled: entry.item.is_enabled(),
+            })
+            .collect()
+    }
+
+    fn get_mut(&mut self, name: &str) -> Result<&mut Entry> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| anyhow!("{name} is not registered"))
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        let entry = self.get_mut(name)?;
+        if enabled {
+            entry.item.enable()
+        } else {
+            entry.item.disable()
+        }
+    }
+
+    /// Flips the entry and returns whether it is now enabled.
+    pub fn toggle(&mut self, name: &str) -> Result<bool> {
+        let entry = self.get_mut(name)?;
+        if entry.item.is_enabled() {
+            entry.item.disable()?;
+        } else {
+            entry.item.enable()?;
+        }
+        Ok(entry.item.is_enabled())
+    }
+
+    /// Enables a group in registration order, or disables it in reverse order.
+    ///
+    /// Every entry is attempted, failures are reported together.
+    pub fn set_group_enabled(&mut self, group: &str, enabled: bool) -> Result<()> {
+        let mut entries = self
+            .entries
+            .iter_mut()
+            .filter(|entry| entry.group == group)
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Err(anyhow!("group {group} has no entries"));
+        }
+        if !enabled {
+            entries.reverse();
+        }
+
+        let errors = entries
+            .into_iter()
+            .filter_map(|entry| {
+                let result = if enabled {
+                    entry.item.enable()
+                } else {
+                    entry.item.disable()
+                };
+                result.err().map(|error| format!("{}: {error}", entry.name))
+            })
+            .collect::<Vec<_>>();
+        combine_errors(errors)
+    }
+
+    /// Flips a whole group based on whether any of its entries is enabled, returns the new state.
+    pub fn toggle_group(&mut self, group: &str) -> Result<bool> {
+        let enabled = !self
+            .entries
+            .iter()
+            .any(|entry| entry.group == group && entry.item.is_enabled());
+        self.set_group_enabled(group, enabled)?;
+        Ok(enabled)
+    }
+
+    /// Disables and drops one entry.
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.name == name)
+            .ok_or_else(|| anyhow!("{name} is not registered"))?;
+        let mut entry = self.entries.remove(index);
+        entry.item.disable()
+    }
+
+    /// Disables and drops every entry, newest first, so stacked patches unwind correctly.
+    pub fn teardown(&mut self) -> Result<()> {
+        let mut errors = vec![];
+        while let Some(mut entry) = self.entries.pop() {
+            if let Err(error) = entry.item.disable() {
+                errors.push(format!("{}: {error}", entry.name));
+            }
+        }
+        combine_errors(errors)
+    }
+}
+
+fn combine_errors(errors: Vec<String>) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!("{}", errors.join("; ")))
+}
+
+/// Process-wide registry.
+pub fn global() -> MutexGuard<'static, Registry> {
+    GLOBAL.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Tears down the process-wide registry, called by `dll_main!` on detach.
+pub fn teardown() -> Result<()> {
+    global().teardown()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Logs `name` to a shared list whenever it is disabled while enabled.
+    struct Recorder {
+        name: &'static str,
+        enabled: bool,
+        fail: bool,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl Toggle for Recorder {
+        fn enable(&mut self) -> Result<()> {
+            self.enabled = true;
+            Ok(())
+        }
+
+        fn disable(&mut self) -> Result<()> {
+            if self.fail {
+                return Err(anyhow!("stuck"));
+            }
+            if self.enabled {
+                self.log.lock().unwrap().push(self.name);
+            }
+            self.enabled = false;
+            Ok(())
+        }
+
+        fn is_enabled(&self) -> bool {
+            self.enabled
+        }
+    }
+
+    fn registry(log: &Arc<Mutex<Vec<&'static str>>>, names: &[&'static str]) -> Registry {
+        let mut registry = Registry::new();
+        for name in names {
+            let recorder = Recorder {
+                name,
+                enabled: true,
+                fail: *name == "stuck",
+                log: log.clone(),
+            };
+            registry.register(name, "group", recorder).unwrap();
+        }
+        registry
+    }
+
+    #[test]
+    fn registers_unique_names() {
+        let log = Arc::default();
+        let mut registry = registry(&log, &["a", "b"]);
+        assert!(registry.contains("a"));
+        let duplicate = Recorder {
+            name: "a",
+            enabled: false,
+            fail: false,
+            log: log.clone(),
+        };
+        assert!(registry.register("a", "other", duplicate).is_err());
+
+        assert!(!registry.toggle("a").unwrap());
+        registry.set_enabled("b", false).unwrap();
+        assert!(registry.set_enabled("missing", true).is_err());
+        assert_eq!(
+            registry.list(),
+            ["a", "b"].map(|name| RegistryEntry {
+                name: name.to_string(),
+                group: "group".to_string(),
+                enabled: false,
+            })
+        );
+        assert!(registry.toggle_group("group").unwrap());
+        assert!(registry.list().iter().all(|entry| entry.enabled));
+    }
+
+    #[test]
+    fn teardown_unwinds_newest_first() {
+        let log = Arc::default();
+        let mut registry = registry(&log, &["a", "b", "c"]);
+        registry.teardown().unwrap();
+        assert_eq!(*log.lock().unwrap(), ["c", "b", "a"]);
+        assert!(registry.list().is_empty());
+
+        registry.teardown().unwrap();
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn teardown_continues_past_failures() {
+        let log = Arc::default();
+        let mut registry = registry(&log, &["a", "stuck", "c"]);
+        let error = registry.teardown().unwrap_err();
+        assert_eq!(error.to_string(), "stuck: stuck");
+        assert_eq!(*log.lock().unwrap(), ["c", "a"]);
+        assert!(registry.list().is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+use std::{cmp::Ordering, fmt, hash, marker::PhantomData};
+
+use crate::*;
+
+/// Typed pointer to a `T` in the memory of `M`.
+///
+/// Remembers the module it was created from, so it prints as `module+offset`.
+/// Comparison and hashing only look at the address.
+pub struct RemotePtr<'a, T, M> {
+    pub address: usize,
+    memory: &'a M,
+    module: Option<&'a Module<M>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T, M: MemoryAccess> RemotePtr<'a, T, M> {
+    pub fn new(memory: &'a M, address: usize) -> Self {
+        Self {
+            address,
+            memory,
+            module: None,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.address == 0
+    }
+
+    /// Fails for null pointers, which [`LocalMemory`] would otherwise dereference.
+    fn check_null(&self) -> Result<()> {
+        if self.is_null() {
+            return Err(anyhow!(
+                "cannot access a {} through a null pointer",
+                std::any::type_name::<T>()
+            ));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn memory(&self) -> &'a M {
+        self.memory
+    }
+
+    /// Reinterprets the pointer as pointing to a `U`.
+    #[inline]
+    pub fn cast<U>(self) -> RemotePtr<'a, U, M> {
+        RemotePtr {
+            address: self.address,
+            memory: self.memory,
+            module: self.module,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Pointer to a `U` located `offset` bytes after this address, e.g. a struct field.
+    #[inline]
+    pub fn offset<U>(self, offset: usize) -> RemotePtr<'a, U, M> {
+        RemotePtr {
+            address: self.address.wrapping_add(offset),
+            ..self.cast()
+        }
+    }
+
+    /// Pointer to the element at `index` when this points to an array of `T`.
+    #[inline]
+    pub fn index(self, index: usize) -> Self {
+        Self {
+            address: self
+                .address
+                .wrapping_add(index.wrapping_mul(mem::size_of::<T>())),
+            ..self
+        }
+    }
+}
+
+impl<T: Pod, M: MemoryAccess> RemotePtr<'_, T, M> {
+    pub fn read(&self) -> Result<T> {
+        self.check_null()?;
+        self.memory.read(self.address)
+    }
+
+    pub fn write(&self, value: T) -> Result<()> {
+        self.check_null()?;
+        self.memory.write(self.address, value)
+    }
+}
+
+impl<'a, T, M: MemoryAccess> RemotePtr<'a, RemotePtr<'a, T, M>, M> {
+    /// Reads the pointer stored at this address, using the target pointer width.
+    pub fn deref(&self) -> Result<RemotePtr<'a, T, M>> {
+        self.check_null()?;
+        let address = self.memory.read_pointer(self.address)?;
+        Ok(RemotePtr {
+            address,
+            memory: self.memory,
+            module: None,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T, M> Clone for RemotePtr<'_, T, M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, M> Copy for RemotePtr<'_, T, M> {}
+
+impl<T, M> PartialEq for RemotePtr<'_, T, M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl<T, M> Eq for RemotePtr<'_, T, M> {}
+
+impl<T, M> PartialOrd for RemotePtr<'_, T, M> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, M> Ord for RemotePtr<'_, T, M> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.address.cmp(&other.address)
+    }
+}
+
+impl<T, M> hash::Hash for RemotePtr<'_, T, M> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.address.hash(state);
+    }
+}
+
+impl<T, M> fmt::Display for RemotePtr<'_, T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.module {
+            Some(module)
+                if self.address >= module.base_address
+                    && self.address < module.base_address + module.size =>
+            {
+                write!(
+                    f,
+                    "{}+{:#X}",
+                    module.name,
+                    self.address - module.base_address
+                )
+            }
+            _ => write!(f, "{:#X}", self.address),
+        }
+    }
+}
+
+impl<T, M> fmt::Debug for RemotePtr<'_, T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RemotePtr<{}>({self})", std::any::type_name::<T>())
+    }
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Typed pointer to `address`, printed relative to the module containing it.
+    pub fn ptr<T>(&self, address: usize) -> RemotePtr<'_, T, M> {
+        RemotePtr {
+            address,
+            memory: &self.memory,
+            module: self.modules.iter().find(|module| {
+                address >= module.base_address && address < module.base_address + module.size
+            }),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Typed pointer `offset` bytes after the module base address.
+    pub fn ptr<T>(&self, offset: usize) -> RemotePtr<'_, T, M> {
+        RemotePtr {
+            address: self.base_address + offset,
+            memory: &self.memory,
+            module: Some(self),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Vector {
+        x: f32,
+        y: f32,
+    }
+
+    unsafe impl Pod for Vector {}
+
+    fn process() -> MockProcess {
+        let mut process = MockProcess::new(1);
+        process.memory.set_pointer_width(4);
+        let mut module = vec![0u8; 0x1000];
+        module[0x10..0x14].copy_from_slice(&0x10000u32.to_le_bytes());
+        process
+            .add_module("game.exe", 0x40_0000, module, Protection::READ)
+            .unwrap();
+        process
+            .add_region(0x10000, vec![0u8; 0x1000], Protection::READ_WRITE)
+            .unwrap();
+        process
+    }
+
+    #[test]
+    fn reads_and_writes_through_pointers() {
+        let process = process();
+        let module = process.get_module_by_name("game.exe").unwrap();
+        let player = module
+            .ptr::<RemotePtr<Vector, MockMemory>>(0x10)
+            .deref()
+            .unwrap();
+        assert_eq!(player.address, 0x10000);
+        player.write(Vector { x: 1.0, y: 2.0 }).unwrap();
+        assert_eq!(player.offset::<f32>(4).read().unwrap(), 2.0);
+
+        let second = player.index(1);
+        assert_eq!(second.address, 0x10008);
+        second.cast::<u64>().write(u64::MAX).unwrap();
+        assert_eq!(process.memory.read::<u64>(0x10008).unwrap(), u64::MAX);
+        assert!(player < second);
+    }
+
+    #[test]
+    fn displays_module_offsets() {
+        let process = process();
+        assert_eq!(process.ptr::<u32>(0x40_0010).to_string(), "game.exe+0x10");
+        assert_eq!(process.ptr::<u32>(0x10000).to_string(), "0x10000");
+        assert_eq!(
+            format!("{:?}", process.ptr::<u8>(0x10000)),
+            "RemotePtr<u8>(0x10000)"
+        );
+    }
+
+    #[test]
+    fn null_and_unmapped_pointers_fail() {
+        let process = process();
+        let null = process.ptr::<u32>(0);
+        assert!(null.is_null());
+        let error = null.read().unwrap_err();
+        assert!(error.to_string().contains("null pointer"), "{error}");
+        assert!(null.write(1).is_err());
+        assert!(process
+            .ptr::<RemotePtr<u32, MockMemory>>(0)
+            .deref()
+            .is_err());
+
+        let unmapped = process.ptr::<u32>(0x20000);
+        assert!(unmapped.read().is_err());
+        assert!(unmapped.write(1).is_err());
+        assert!(process.ptr::<u32>(0x40_0000).write(1).is_err(), "read-only");
+        let dangling = process.ptr::<RemotePtr<u32, MockMemory>>(0x40_0020);
+        assert!(dangling.deref().unwrap().read().is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use std::{fmt, marker::PhantomData};
+
+use crate::*;
+
+/// A value that can be decoded from target memory, the building block of `#[derive(RemoteStruct)]`.
+///
+/// Sizes depend on the target pointer width because of [`Pointer`] fields. Like `sizeof` in C, a size is the
+/// distance between array elements, tail padding included.
+pub trait RemoteField: Sized {
+    /// Size in a 32-bit target.
+    const SIZE_32: usize;
+    /// Size in a 64-bit target.
+    const SIZE_64: usize;
+    /// Alignment in a 32-bit target.
+    const ALIGN_32: usize;
+    /// Alignment in a 64-bit target.
+    const ALIGN_64: usize;
+
+    /// Decodes the value from the start of `bytes`, which holds at least [`RemoteField::remote_size`] bytes.
+    fn decode(bytes: &[u8], pointer_width: usize) -> Self;
+
+    #[inline]
+    fn remote_size(pointer_width: usize) -> usize {
+        if pointer_width == 4 {
+            Self::SIZE_32
+        } else {
+            Self::SIZE_64
+        }
+    }
+
+    /// Reads the value at `address` with a single read.
+    fn read_remote<M: MemoryAccess>(memory: &M, address: usize) -> Result<Self> {
+        let pointer_width = memory.pointer_width();
+        let mut bytes = vec![0u8; Self::remote_size(pointer_width)];
+        memory.read_bytes(address, &mut bytes)?;
+        Ok(Self::decode(&bytes, pointer_width))
+    }
+}
+
+macro_rules! impl_remote_field {
+    ($($type:ty),*) => {
+        $(impl RemoteField for $type {
+            const SIZE_32: usize = mem::size_of::<$type>();
+            const SIZE_64: usize = mem::size_of::<$type>();
+            const ALIGN_32: usize = mem::size_of::<$type>();
+            const ALIGN_64: usize = mem::size_of::<$type>();
+
+            #[inline]
+            fn decode(bytes: &[u8], _pointer_width: usize) -> Self {
+                let bytes = &bytes[..mem::size_of::<$type>()];
+                unsafe { ptr::read_unaligned(bytes.as_ptr() as *const $type) }
+            }
+        })*
+    };
+}
+
+impl_remote_field!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+impl<T: RemoteField, const N: usize> RemoteField for [T; N] {
+    const SIZE_32: usize = T::SIZE_32 * N;
+    const SIZE_64: usize = T::SIZE_64 * N;
+    const ALIGN_32: usize = T::ALIGN_32;
+    const ALIGN_64: usize = T::ALIGN_64;
+
+    fn decode(bytes: &[u8], pointer_width: usize) -> Self {
+        let size = T::remote_size(pointer_width);
+        std::array::from_fn(|index| T::decode(&bytes[index * size..], pointer_width))
+    }
+}
+
+/// A pointer stored in target memory, sized by the target pointer width.
+pub struct Pointer<T> {
+    pub address: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Pointer<T> {
+    pub fn new(address: usize) -> Self {
+        Self {
+            address,
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.address == 0
+    }
+
+    /// Attaches the pointer to a backend so it can be followed.
+    pub fn bind<M: MemoryAccess>(self, memory: &M) -> RemotePtr<'_, T, M> {
+        RemotePtr::new(memory, self.address)
+    }
+}
+
+impl<T> Clone for Pointer<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Pointer<T> {}
+
+impl<T> PartialEq for Pointer<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl<T> Eq for Pointer<T> {}
+
+impl<T> fmt::Debug for Pointer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Pointer<{}>({:#X})",
+            std::any::type_name::<T>(),
+            self.address
+        )
+    }
+}
+
+impl<T> RemoteField for Pointer<T> {
+    const SIZE_32: usize = 4;
+    const SIZE_64: usize = 8;
+    const ALIGN_32: usize = 4;
+    const ALIGN_64: usize = 8;
+
+    fn decode(bytes: &[u8], pointer_width: usize) -> Self {
+        let mut address = [0u8; 8];
+        let pointer_width = pointer_width.min(address.len());
+        address[..pointer_width].copy_from_slice(&bytes[..pointer_width]);
+        Self::new(u64::from_le_bytes(address) as usize)
+    }
+}
+
+/// Implemented by `#[derive(RemoteStruct)]`, connects a struct to its generated view type.
+///
+/// The view reads single fields lazily, while [`RemoteField::read_remote`] reads the whole struct at once.
+pub trait RemoteStruct: RemoteField {
+    type View<'a, M: MemoryAccess + 'a>;
+
+    fn view<M: MemoryAccess>(memory: &M, address: usize) -> Self::View<'_, M>;
+}
+
+/// Used by the derive macro to compute struct sizes in constant context.
+#[doc(hidden)]
+pub const fn const_max(left: usize, right: usize) -> usize {
+    if left > right {
+        left
+    } else {
+        right
+    }
+}
+
+impl<'a, T: RemoteStruct, M: MemoryAccess> RemotePtr<'a, T, M> {
+    /// Returns the generated view of the struct this points to.
+    pub fn view(self) -> T::View<'a, M> {
+        T::view(self.memory(), self.address)
+    }
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Reads a `#[derive(RemoteStruct)]` struct, or any other [`RemoteField`], in one read.
+    pub fn read_struct<T: RemoteField>(&self, address: usize) -> Result<T> {
+        T::read_remote(&self.memory, address)
+    }
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Reads a [`RemoteField`] located `offset` bytes after the module base address in one read.
+    pub fn read_struct<T: RemoteField>(&self, offset: usize) -> Result<T> {
+        T::read_remote(&self.memory, self.base_address + offset)
+    }
+}
@@ -0,0 +1,1275 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::*;
+
+/// Regions are scanned and stored in blocks of this size, so no block ever needs more than a few MiB of RAM.
+pub(crate) const BLOCK_SIZE: usize = 0x10_0000;
+
+/// Results beyond this many bytes are spilled to a temporary file.
+const DEFAULT_MEMORY_LIMIT: usize = 0x1000_0000;
+
+static SPILL_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Type of the values a [`Scanner`] looks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    /// Byte array of the given length.
+    Bytes(usize),
+}
+
+impl ValueType {
+    pub fn size(self) -> usize {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+            Self::Bytes(length) => length,
+        }
+    }
+
+    /// Natural alignment of the type, byte arrays are unaligned.
+    pub fn default_alignment(self) -> usize {
+        match self {
+            Self::Bytes(_) => 1,
+            _ => self.size(),
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::U8 => f.write_str("u8"),
+            Self::U16 => f.write_str("u16"),
+            Self::U32 => f.write_str("u32"),
+            Self::U64 => f.write_str("u64"),
+            Self::I8 => f.write_str("i8"),
+            Self::I16 => f.write_str("i16"),
+            Self::I32 => f.write_str("i32"),
+            Self::I64 => f.write_str("i64"),
+            Self::F32 => f.write_str("f32"),
+            Self::F64 => f.write_str("f64"),
+            Self::Bytes(length) => write!(f, "[u8; {length}]"),
+        }
+    }
+}
+
+/// A value of one of the [`ValueType`]s.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Self::U8(_) => ValueType::U8,
+            Self::U16(_) => ValueType::U16,
+            Self::U32(_) => ValueType::U32,
+            Self::U64(_) => ValueType::U64,
+            Self::I8(_) => ValueType::I8,
+            Self::I16(_) => ValueType::I16,
+            Self::I32(_) => ValueType::I32,
+            Self::I64(_) => ValueType::I64,
+            Self::F32(_) => ValueType::F32,
+            Self::F64(_) => ValueType::F64,
+            Self::Bytes(bytes) => ValueType::Bytes(bytes.len()),
+        }
+    }
+
+    /// Decodes a little endian value, `bytes` must hold at least `value_type.size()` bytes.
+    pub fn from_bytes(value_type: ValueType, bytes: &[u8]) -> Self {
+        match value_type {
+            ValueType::U8 => Self::U8(ScanNumber::from_le(bytes)),
+            ValueType::U16 => Self::U16(ScanNumber::from_le(bytes)),
+            ValueType::U32 => Self::U32(ScanNumber::from_le(bytes)),
+            ValueType::U64 => Self::U64(ScanNumber::from_le(bytes)),
+            ValueType::I8 => Self::I8(ScanNumber::from_le(bytes)),
+            ValueType::I16 => Self::I16(ScanNumber::from_le(bytes)),
+            ValueType::I32 => Self::I32(ScanNumber::from_le(bytes)),
+            ValueType::I64 => Self::I64(ScanNumber::from_le(bytes)),
+            ValueType::F32 => Self::F32(ScanNumber::from_le(bytes)),
+            ValueType::F64 => Self::F64(ScanNumber::from_le(bytes)),
+            ValueType::Bytes(length) => Self::Bytes(bytes[..length].to_vec()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::U8(value) => value.to_le_bytes().to_vec(),
+            Self::U16(value) => value.to_le_bytes().to_vec(),
+            Self::U32(value) => value.to_le_bytes().to_vec(),
+            Self::U64(value) => value.to_le_bytes().to_vec(),
+            Self::I8(value) => value.to_le_bytes().to_vec(),
+            Self::I16(value) => value.to_le_bytes().to_vec(),
+            Self::I32(value) => value.to_le_bytes().to_vec(),
+            Self::I64(value) => value.to_le_bytes().to_vec(),
+            Self::F32(value) => value.to_le_bytes().to_vec(),
+            Self::F64(value) => value.to_le_bytes().to_vec(),
+            Self::Bytes(bytes) => bytes.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::U8(value) => write!(f, "{value}"),
+            Self::U16(value) => write!(f, "{value}"),
+            Self::U32(value) => write!(f, "{value}"),
+            Self::U64(value) => write!(f, "{value}"),
+            Self::I8(value) => write!(f, "{value}"),
+            Self::I16(value) => write!(f, "{value}"),
+            Self::I32(value) => write!(f, "{value}"),
+            Self::I64(value) => write!(f, "{value}"),
+            Self::F32(value) => write!(f, "{value}"),
+            Self::F64(value) => write!(f, "{value}"),
+            Self::Bytes(bytes) => write!(f, "{bytes:02X?}"),
+        }
+    }
+}
+
+/// Condition of the scan that builds the initial result set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FirstScan {
+    Exact(Value),
+    /// Inclusive range.
+    Range(Value, Value),
+    /// Keeps every address, only useful as the base of a [`NextScan`].
+    Unknown,
+}
+
+/// Condition that narrows the results, comparisons are against the values seen by the previous scan.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NextScan {
+    Exact(Value),
+    /// Inclusive range.
+    Range(Value, Value),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    IncreasedBy(Value),
+    DecreasedBy(Value),
+}
+
+/// An address still in the results with the value it had during the last scan.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScanMatch {
+    pub address: usize,
+    pub value: Value,
+}
+
+/// How floating point values are compared by exact, changed, unchanged and increased or decreased by scans.
+///
+/// Integers and byte arrays always compare exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FloatCompare {
+    #[default]
+    Exact,
+    /// Equal after rounding both values to this many decimal places, at most [`FloatCompare::MAX_DECIMALS`].
+    Rounded(u32),
+    /// Equal after cutting both values off after this many decimal places, at most [`FloatCompare::MAX_DECIMALS`].
+    Truncated(u32),
+    /// At most this far apart.
+    Epsilon(f64),
+}
+
+impl FloatCompare {
+    /// More decimal places than an `f64` can hold are treated as this many.
+    pub const MAX_DECIMALS: u32 = 15;
+
+    pub fn matches(self, value: f64, target: f64) -> bool {
+        match self {
+            Self::Exact => value == target,
+            Self::Rounded(decimals) => scaled_eq(value, target, decimals, f64::round),
+            Self::Truncated(decimals) => scaled_eq(value, target, decimals, f64::trunc),
+            Self::Epsilon(epsilon) => (value - target).abs() <= epsilon,
+        }
+    }
+}
+
+/// Compares `value` and `target` after scaling them by `10^decimals` and applying `cut`, values too large to scale
+/// compare exactly.
+fn scaled_eq(value: f64, target: f64, decimals: u32, cut: fn(f64) -> f64) -> bool {
+    let scale = 10f64.powi(decimals.min(FloatCompare::MAX_DECIMALS) as i32);
+    let (scaled_value, scaled_target) = (value * scale, target * scale);
+    if !scaled_value.is_finite() || !scaled_target.is_finite() {
+        return value == target;
+    }
+    cut(scaled_value) == cut(scaled_target)
+}
+
+/// NaN and denormal floats are almost always garbage rather than game values, so they never match a float scan.
+#[inline]
+fn is_float_noise(value: f64, is_subnormal: bool) -> bool {
+    value.is_nan() || is_subnormal
+}
+
+trait ScanNumber: Copy + PartialOrd + Send + Sync + 'static {
+    fn from_le(bytes: &[u8]) -> Self;
+
+    fn from_value(value: &Value) -> Option<Self>;
+
+    /// `new - old`, wrapping for integers.
+    fn difference(new: Self, old: Self) -> Self;
+
+    /// Whether the value is float noise that never matches.
+    fn is_noise(self) -> bool;
+
+    fn equals(self, other: Self, compare: FloatCompare) -> bool;
+}
+
+macro_rules! scan_number {
+    (@common $ty:ty, $variant:ident) => {
+        #[inline]
+        fn from_le(bytes: &[u8]) -> Self {
+            <$ty>::from_le_bytes(bytes[..mem::size_of::<$ty>()].try_into().unwrap())
+        }
+
+        fn from_value(value: &Value) -> Option<Self> {
+            match value {
+                Value::$variant(value) => Some(*value),
+                _ => None,
+            }
+        }
+    };
+    (integer $($ty:ty => $variant:ident;)*) => {$(
+        impl ScanNumber for $ty {
+            scan_number!(@common $ty, $variant);
+
+            #[inline]
+            fn difference(new: Self, old: Self) -> Self {
+                new.wrapping_sub(old)
+            }
+
+            #[inline]
+            fn is_noise(self) -> bool {
+                false
+            }
+
+            #[inline]
+            fn equals(self, other: Self, _compare: FloatCompare) -> bool {
+                self == other
+            }
+        }
+    )*};
+    (float $($ty:ty => $variant:ident;)*) => {$(
+        impl ScanNumber for $ty {
+            scan_number!(@common $ty, $variant);
+
+            #[inline]
+            fn difference(new: Self, old: Self) -> Self {
+                new - old
+            }
+
+            #[inline]
+            fn is_noise(self) -> bool {
+                is_float_noise(self as f64, self.is_subnormal())
+            }
+
+            #[inline]
+            fn equals(self, other: Self, compare: FloatCompare) -> bool {
+                compare.matches(self as f64, other as f64)
+            }
+        }
+    )*};
+}
+
+scan_number! {
+    integer
+    u8 => U8;
+    u16 => U16;
+    u32 => U32;
+    u64 => U64;
+    i8 => I8;
+    i16 => I16;
+    i32 => I32;
+    i64 => I64;
+}
+
+scan_number! {
+    float
+    f32 => F32;
+    f64 => F64;
+}
+
+impl Value {
+    /// Compares two values of the same type, floats according to `compare` and never equal when NaN or denormal.
+    pub fn approx_eq(&self, other: &Value, compare: FloatCompare) -> bool {
+        fn float<T: ScanNumber>(value: T, other: T, compare: FloatCompare) -> bool {
+            !value.is_noise() && !other.is_noise() && value.equals(other, compare)
+        }
+
+        match (self, other) {
+            (Self::F32(value), Self::F32(other)) => float(*value, *other, compare),
+            (Self::F64(value), Self::F64(other)) => float(*value, *other, compare),
+            _ => self == other,
+        }
+    }
+}
+
+/// Tests the current bytes of a slot against the bytes seen by the previous scan.
+type Matcher = Box<dyn Fn(&[u8], &[u8]) -> bool>;
+
+enum Condition<T> {
+    Exact(T),
+    Range(T, T),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    IncreasedBy(T),
+    DecreasedBy(T),
+}
+
+impl<T: ScanNumber> Condition<T> {
+    #[inline]
+    fn matches(&self, new: T, old: T, compare: FloatCompare) -> bool {
+        if new.is_noise() {
+            return false;
+        }
+        match *self {
+            Self::Exact(value) => new.equals(value, compare),
+            Self::Range(low, high) => low <= new && new <= high,
+            Self::Changed => !new.equals(old, compare),
+            Self::Unchanged => new.equals(old, compare),
+            Self::Increased => new > old,
+            Self::Decreased => new < old,
+            Self::IncreasedBy(value) => T::difference(new, old).equals(value, compare),
+            Self::DecreasedBy(value) => T::difference(old, new).equals(value, compare),
+        }
+    }
+}
+
+fn number<T: ScanNumber>(value: &Value, value_type: ValueType) -> Result<T> {
+    T::from_value(value)
+        .ok_or_else(|| anyhow!("expected a {value_type} value, got {}", value.value_type()))
+}
+
+fn number_matcher<T: ScanNumber>(
+    scan: &NextScan,
+    value_type: ValueType,
+    compare: FloatCompare,
+) -> Result<Matcher> {
+    let condition = match scan {
+        NextScan::Exact(value) => Condition::Exact(number::<T>(value, value_type)?),
+        NextScan::Range(low, high) => Condition::Range(
+            number::<T>(low, value_type)?,
+            number::<T>(high, value_type)?,
+        ),
+        NextScan::Changed => Condition::Changed,
+        NextScan::Unchanged => Condition::Unchanged,
+        NextScan::Increased => Condition::Increased,
+        NextScan::Decreased => Condition::Decreased,
+        NextScan::IncreasedBy(value) => Condition::IncreasedBy(number::<T>(value, value_type)?),
+        NextScan::DecreasedBy(value) => Condition::DecreasedBy(number::<T>(value, value_type)?),
+    };
+    Ok(Box::new(move |new, old| {
+        condition.matches(T::from_le(new), T::from_le(old), compare)
+    }))
+}
+
+fn bytes_matcher(scan: &NextScan, length: usize) -> Result<Matcher> {
+    match scan {
+        NextScan::Exact(Value::Bytes(bytes)) if bytes.len() == length => {
+            let bytes = bytes.clone();
+            Ok(Box::new(move |new, _| new == bytes.as_slice()))
+        }
+        NextScan::Exact(value) => Err(anyhow!(
+            "expected a [u8; {length}] value, got {}",
+            value.value_type()
+        )),
+        NextScan::Changed => Ok(Box::new(|new, old| new != old)),
+        NextScan::Unchanged => Ok(Box::new(|new, old| new == old)),
+        _ => Err(anyhow!(
+            "byte arrays only support exact, changed and unchanged scans"
+        )),
+    }
+}
+
+/// Builds the test for `scan`.
+fn matcher(value_type: ValueType, scan: &NextScan, compare: FloatCompare) -> Result<Matcher> {
+    match value_type {
+        ValueType::U8 => number_matcher::<u8>(scan, value_type, compare),
+        ValueType::U16 => number_matcher::<u16>(scan, value_type, compare),
+        ValueType::U32 => number_matcher::<u32>(scan, value_type, compare),
+        ValueType::U64 => number_matcher::<u64>(scan, value_type, compare),
+        ValueType::I8 => number_matcher::<i8>(scan, value_type, compare),
+        ValueType::I16 => number_matcher::<i16>(scan, value_type, compare),
+        ValueType::I32 => number_matcher::<i32>(scan, value_type, compare),
+        ValueType::I64 => number_matcher::<i64>(scan, value_type, compare),
+        ValueType::F32 => number_matcher::<f32>(scan, value_type, compare),
+        ValueType::F64 => number_matcher::<f64>(scan, value_type, compare),
+        ValueType::Bytes(length) => bytes_matcher(scan, length),
+    }
+}
+
+/// Builds the test for a first scan, `None` keeps every slot.
+fn first_matcher(
+    value_type: ValueType,
+    scan: &FirstScan,
+    compare: FloatCompare,
+) -> Result<Option<Matcher>> {
+    let scan = match scan {
+        FirstScan::Exact(value) => NextScan::Exact(value.clone()),
+        FirstScan::Range(low, high) => NextScan::Range(low.clone(), high.clone()),
+        FirstScan::Unknown => {
+            return Ok(match value_type {
+                ValueType::F32 => Some(Box::new(|new, _| !f32::from_le(new).is_noise())),
+                ValueType::F64 => Some(Box::new(|new, _| !f64::from_le(new).is_noise())),
+                _ => None,
+            })
+        }
+    };
+    matcher(value_type, &scan, compare).map(Some)
+}
+
+/// Temporary file holding block payloads that did not fit into the memory limit, deleted when dropped.
+struct SpillFile {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl SpillFile {
+    fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "cheatlib-scan-{}-{}.bin",
+            std::process::id(),
+            SPILL_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|error| anyhow!("failed to create {}: {error}", path.display()))?;
+        Ok(Self { file, path, len: 0 })
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<u64> {
+        let offset = self.len;
+        (&self.file).seek(SeekFrom::Start(offset))?;
+        (&self.file).write_all(data)?;
+        self.len += data.len() as u64;
+        Ok(offset)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; len];
+        (&self.file).seek(SeekFrom::Start(offset))?;
+        (&self.file).read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+enum Payload {
+    Memory(Vec<u8>),
+    Spilled { offset: u64, len: usize },
+}
+
+/// Matches of one block. Slot `i` is the value at `base_address + i * alignment`.
+struct Block {
+    base_address: usize,
+    slots: usize,
+    payload: Payload,
+}
+
+const INDEX_ALL: u8 = 0;
+const INDEX_BITMAP: u8 = 1;
+const INDEX_DELTAS: u8 = 2;
+const VALUES_PACKED: u8 = 0;
+const VALUES_RAW: u8 = 1;
+
+fn write_varint(output: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn read_varint(input: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = input[*position];
+        *position += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// Encodes the matched slot `indices` and their values, picking whichever representation is smallest.
+///
+/// Payload layout: index tag, value tag, index length as u64, index data, value data.
+fn encode_block(
+    slots: usize,
+    indices: &[u32],
+    data: &[u8],
+    alignment: usize,
+    size: usize,
+) -> Vec<u8> {
+    let (index_tag, index) = if indices.len() == slots {
+        (INDEX_ALL, vec![])
+    } else {
+        let mut deltas = vec![];
+        let mut previous = 0;
+        for &index in indices {
+            write_varint(&mut deltas, index as usize - previous);
+            previous = index as usize;
+        }
+        if deltas.len() <= slots.div_ceil(8) {
+            (INDEX_DELTAS, deltas)
+        } else {
+            let mut bitmap = vec![0u8; slots.div_ceil(8)];
+            for &index in indices {
+                bitmap[index as usize / 8] |= 1 << (index % 8);
+            }
+            (INDEX_BITMAP, bitmap)
+        }
+    };
+
+    let raw_length = indices
+        .last()
+        .map_or(0, |&last| last as usize * alignment + size);
+    let mut payload = vec![index_tag, VALUES_PACKED];
+    payload.extend_from_slice(&(index.len() as u64).to_le_bytes());
+    payload.extend_from_slice(&index);
+    if raw_length < indices.len() * size {
+        payload[1] = VALUES_RAW;
+        payload.extend_from_slice(&data[..raw_length]);
+    } else {
+        for &index in indices {
+            let offset = index as usize * alignment;
+            payload.extend_from_slice(&data[offset..offset + size]);
+        }
+    }
+    payload
+}
+
+/// Decodes a payload written by [`encode_block`] into slot indices and a lookup for their values.
+struct DecodedBlock<'a> {
+    indices: Vec<u32>,
+    values: &'a [u8],
+    raw: bool,
+}
+
+impl<'a> DecodedBlock<'a> {
+    fn decode(payload: &'a [u8], slots: usize) -> Self {
+        let index_length = u64::from_le_bytes(payload[2..10].try_into().unwrap()) as usize;
+        let index = &payload[10..10 + index_length];
+        let indices = match payload[0] {
+            INDEX_ALL => (0..slots as u32).collect(),
+            INDEX_BITMAP => (0..slots as u32)
+                .filter(|&slot| index[slot as usize / 8] & (1 << (slot % 8)) != 0)
+                .collect(),
+            _ => {
+                let mut indices = vec![];
+                let mut position = 0;
+                let mut slot = 0;
+                while position < index.len() {
+                    slot += read_varint(index, &mut position);
+                    indices.push(slot as u32);
+                }
+                indices
+            }
+        };
+        Self {
+            indices,
+            values: &payload[10 + index_length..],
+            raw: payload[1] == VALUES_RAW,
+        }
+    }
+
+    /// Value of the `nth` match.
+    #[inline]
+    fn value(&self, nth: usize, alignment: usize, size: usize) -> &'a [u8] {
+        let offset = if self.raw {
+            self.indices[nth] as usize * alignment
+        } else {
+            nth * size
+        };
+        &self.values[offset..offset + size]
+    }
+}
+
+/// Result set of a [`Scanner`], block payloads move to a [`SpillFile`] once `memory_limit` is exceeded.
+struct ScanResults {
+    blocks: Vec<Block>,
+    /// Alignment of the first scan, slot indices are only meaningful with it.
+    alignment: usize,
+    count: usize,
+    in_memory: usize,
+    memory_limit: usize,
+    spill: Option<SpillFile>,
+}
+
+impl ScanResults {
+    fn new(alignment: usize, memory_limit: usize) -> Self {
+        Self {
+            blocks: vec![],
+            alignment,
+            count: 0,
+            in_memory: 0,
+            memory_limit,
+            spill: None,
+        }
+    }
+
+    fn push(
+        &mut self,
+        base_address: usize,
+        slots: usize,
+        count: usize,
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        let payload = if self.in_memory + payload.len() <= self.memory_limit {
+            self.in_memory += payload.len();
+            Payload::Memory(payload)
+        } else {
+            let spill = match &mut self.spill {
+                Some(spill) => spill,
+                spill => spill.insert(SpillFile::create()?),
+            };
+            Payload::Spilled {
+                offset: spill.append(&payload)?,
+                len: payload.len(),
+            }
+        };
+        self.count += count;
+        self.blocks.push(Block {
+            base_address,
+            slots,
+            payload,
+        });
+        Ok(())
+    }
+
+    fn payload<'b>(&'b self, block: &'b Block) -> Result<Cow<'b, [u8]>> {
+        match &block.payload {
+            Payload::Memory(payload) => Ok(Cow::Borrowed(payload)),
+            Payload::Spilled { offset, len } => {
+                let spill = self
+                    .spill
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("scan results lost their spill file"))?;
+                Ok(Cow::Owned(spill.read(*offset, *len)?))
+            }
+        }
+    }
+}
+
+/// Whether `region` is readable and safe to read in bulk.
+///
+/// The kernel's `[vvar]` pages are listed as readable on linux but some of them fault when touched.
+pub(crate) fn is_scannable(region: &MemoryRegion) -> bool {
+    region.is_readable()
+        && !region
+            .path
+            .as_deref()
+            .is_some_and(|path| path.starts_with("[vvar"))
+}
+
+/// Bytes of a block and the ranges of it that could be read.
+pub(crate) struct BlockData {
+    pub(crate) data: Vec<u8>,
+    pub(crate) valid: Vec<Range<usize>>,
+}
+
+impl BlockData {
+    /// Reads directly when `regions` show the whole range readable, otherwise page by page.
+    ///
+    /// Checking first keeps [`LocalMemory`] from touching memory that was freed since the regions were listed.
+    pub(crate) fn read<M: MemoryAccess>(
+        memory: &M,
+        regions: &[MemoryRegion],
+        address: usize,
+        len: usize,
+    ) -> Result<Self> {
+        let index = regions.partition_point(|region| region.end_address() <= address);
+        let readable = regions.get(index).is_some_and(|region| {
+            region.contains(address)
+                && region.is_readable()
+                && region.end_address() >= address + len
+        });
+        if readable {
+            let mut data = vec![0u8; len];
+            if memory.read_bytes(address, &mut data).is_ok() {
+                return Ok(Self {
+                    data,
+                    valid: std::iter::once(0..len).collect(),
+                });
+            }
+        }
+        let partial = memory.read_partial(address, len)?;
+        Ok(Self {
+            data: partial.data,
+            valid: partial.valid_ranges,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn is_valid(&self, range: Range<usize>) -> bool {
+        let index = self.valid.partition_point(|valid| valid.end < range.end);
+        self.valid
+            .get(index)
+            .is_some_and(|valid| valid.start <= range.start)
+    }
+}
+
+/// Cheat Engine style value scanner over the regions of a process.
+///
+/// A [`FirstScan`] builds the result set, every [`NextScan`] narrows it. Results are kept per 1 MiB block as a
+/// bitmap or delta encoded slot list, whichever is smaller, and spill to a temporary file beyond the memory limit.
+/// By default only writable memory is scanned and values are aligned to their size. Float scans never match NaN or
+/// denormal values.
+pub struct Scanner<'a, M> {
+    memory: &'a M,
+    value_type: ValueType,
+    alignment: usize,
+    range: Range<usize>,
+    include_read_only: bool,
+    memory_limit: usize,
+    float_compare: FloatCompare,
+    results: Option<ScanResults>,
+}
+
+impl<'a, M: MemoryAccess> Scanner<'a, M> {
+    pub fn new(memory: &'a M, value_type: ValueType) -> Self {
+        Self {
+            memory,
+            value_type,
+            alignment: value_type.default_alignment(),
+            range: 0..usize::MAX,
+            include_read_only: false,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            float_compare: FloatCompare::Exact,
+            results: None,
+        }
+    }
+
+    /// Only considers addresses that are a multiple of `alignment`, which must be a power of two up to a page.
+    ///
+    /// Existing results keep the alignment of their first scan, the new one applies from the next first scan on.
+    pub fn with_alignment(self, alignment: usize) -> Self {
+        Self { alignment, ..self }
+    }
+
+    /// Limits the first scan to values inside `range`.
+    pub fn with_range(self, range: Range<usize>) -> Self {
+        Self { range, ..self }
+    }
+
+    /// Also scans memory that is not writable.
+    pub fn with_read_only(self, include_read_only: bool) -> Self {
+        Self {
+            include_read_only,
+            ..self
+        }
+    }
+
+    /// Bytes of results kept in RAM before the rest is written to a temporary file.
+    pub fn with_memory_limit(self, memory_limit: usize) -> Self {
+        Self {
+            memory_limit,
+            ..self
+        }
+    }
+
+    /// How float values are compared, see [`FloatCompare`]. Can be changed between scans.
+    pub fn with_float_compare(self, float_compare: FloatCompare) -> Self {
+        Self {
+            float_compare,
+            ..self
+        }
+    }
+
+    /// Changes how float values are compared from the next scan on.
+    pub fn set_float_compare(&mut self, float_compare: FloatCompare) {
+        self.float_compare = float_compare;
+    }
+
+    #[inline]
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    #[inline]
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Number of addresses in the results, zero before the first scan.
+    pub fn count(&self) -> usize {
+        self.results.as_ref().map_or(0, |results| results.count)
+    }
+
+    /// Whether a first scan has been done.
+    pub fn has_results(&self) -> bool {
+        self.results.is_some()
+    }
+
+    /// Whether results were written to a temporary file.
+    pub fn is_spilled(&self) -> bool {
+        self.results
+            .as_ref()
+            .is_some_and(|results| results.spill.is_some())
+    }
+
+    /// Drops the results, the next scan has to be a first scan.
+    pub fn reset(&mut self) {
+        self.results = None;
+    }
+
+    /// Splits the regions to scan into `(address, slots)` blocks.
+    fn blocks(&self, regions: &[MemoryRegion]) -> Vec<(usize, usize)> {
+        let size = self.value_type.size();
+        let mut blocks = vec![];
+        for region in regions {
+            if !is_scannable(region) || !(self.include_read_only || region.is_writable()) {
+                continue;
+            }
+            let start = region.base_address.max(self.range.start);
+            let end = region.end_address().min(self.range.end);
+            let mut address = start.next_multiple_of(self.alignment);
+            while address.saturating_add(size) <= end {
+                let block_end = (address + BLOCK_SIZE).min(end);
+                let slots = (block_end - address)
+                    .div_ceil(self.alignment)
+                    .min((end - size - address) / self.alignment + 1);
+                blocks.push((address, slots));
+                address += slots * self.alignment;
+            }
+        }
+        blocks
+    }
+
+    fn check_alignment(&self) -> Result<()> {
+        if !self.alignment.is_power_of_two() || self.alignment > PAGE_SIZE {
+            return Err(anyhow!(
+                "scan alignment {} is not a power of two up to {:#0x}",
+                self.alignment,
+                PAGE_SIZE
+            ));
+        }
+        if self.value_type.size() == 0 {
+            return Err(anyhow!("cannot scan for empty byte arrays"));
+        }
+        Ok(())
+    }
+
+    /// Scans every region and replaces the results, returns the number of matches.
+    pub fn first_scan(&mut self, scan: FirstScan) -> Result<usize> {
+        self.check_alignment()?;
+        let matcher = first_matcher(self.value_type, &scan, self.float_compare)?;
+        let size = self.value_type.size();
+        let regions = self.memory.regions()?;
+
+        let mut results = ScanResults::new(self.alignment, self.memory_limit);
+        let mut indices = vec![];
+        for (address, slots) in self.blocks(&regions) {
+            let length = (slots - 1) * self.alignment + size;
+            let block = BlockData::read(self.memory, &regions, address, length)?;
+            indices.clear();
+            for slot in 0..slots {
+                let offset = slot * self.alignment;
+                if !block.is_valid(offset..offset + size) {
+                    continue;
+                }
+                let value = &block.data[offset..offset + size];
+                if matcher.as_ref().is_none_or(|matcher| matcher(value, value)) {
+                    indices.push(slot as u32);
+                }
+            }
+            if indices.is_empty() {
+                continue;
+            }
+            let payload = encode_block(slots, &indices, &block.data, self.alignment, size);
+            results.push(address, slots, indices.len(), payload)?;
+        }
+
+        let count = results.count;
+        self.results = Some(results);
+        Ok(count)
+    }
+
+    /// Rescans the current results, keeping those that satisfy `scan`, returns the number of matches.
+    ///
+    /// The previous results are kept if the scan fails.
+    pub fn next_scan(&mut self, scan: NextScan) -> Result<usize> {
+        let matcher = matcher(self.value_type, &scan, self.float_compare)?;
+        let previous = self
+            .results
+            .as_ref()
+            .ok_or_else(|| anyhow!("a first scan is required before a next scan"))?;
+        let alignment = previous.alignment;
+        let size = self.value_type.size();
+        let regions = self.memory.regions()?;
+
+        let mut results = ScanResults::new(alignment, self.memory_limit);
+        let mut indices = vec![];
+        for block in &previous.blocks {
+            let payload = previous.payload(block)?;
+            let decoded = DecodedBlock::decode(&payload, block.slots);
+            let length = (block.slots - 1) * alignment + size;
+            let current = BlockData::read(self.memory, &regions, block.base_address, length)?;
+
+            indices.clear();
+            for (nth, &slot) in decoded.indices.iter().enumerate() {
+                let offset = slot as usize * alignment;
+                if !current.is_valid(offset..offset + size) {
+                    continue;
+                }
+                let old = decoded.value(nth, alignment, size);
+                if matcher(&current.data[offset..offset + size], old) {
+                    indices.push(slot);
+                }
+            }
+            if indices.is_empty() {
+                continue;
+            }
+            let payload = encode_block(block.slots, &indices, &current.data, alignment, size);
+            results.push(block.base_address, block.slots, indices.len(), payload)?;
+        }
+
+        let count = results.count;
+        self.results = Some(results);
+        Ok(count)
+    }
+
+    /// Returns up to `limit` results in address order with the values seen by the last scan.
+    pub fn results(&self, limit: usize) -> Result<Vec<ScanMatch>> {
+        let Some(results) = &self.results else {
+            return Ok(vec![]);
+        };
+        let size = self.value_type.size();
+        let mut matches = vec![];
+        for block in &results.blocks {
+            if matches.len() >= limit {
+                break;
+            }
+            let payload = results.payload(block)?;
+            let decoded = DecodedBlock::decode(&payload, block.slots);
+            for (nth, &slot) in decoded
+                .indices
+                .iter()
+                .enumerate()
+                .take(limit - matches.len())
+            {
+                matches.push(ScanMatch {
+                    address: block.base_address + slot as usize * results.alignment,
+                    value: Value::from_bytes(
+                        self.value_type,
+                        decoded.value(nth, results.alignment, size),
+                    ),
+                });
+            }
+        }
+        Ok(matches)
+    }
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Value [`Scanner`] over the regions of this process.
+    pub fn scanner(&self, value_type: ValueType) -> Scanner<'_, M> {
+        Scanner::new(&self.memory, value_type)
+    }
+
+    /// Reads a value of `value_type` at `address`, compare it with [`Value::approx_eq`].
+    pub fn read_value(&self, address: usize, value_type: ValueType) -> Result<Value> {
+        let mut bytes = vec![0u8; value_type.size()];
+        self.memory.read_bytes(address, &mut bytes)?;
+        Ok(Value::from_bytes(value_type, &bytes))
+    }
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Value [`Scanner`] limited to this module, including its read-only sections.
+    pub fn scanner(&self, value_type: ValueType) -> Scanner<'_, M> {
+        Scanner::new(&self.memory, value_type)
+            .with_range(self.base_address..self.base_address + self.size)
+            .with_read_only(true)
+    }
+
+    /// Reads a value of `value_type` located `offset` bytes after the module base address.
+    pub fn read_value(&self, offset: usize, value_type: ValueType) -> Result<Value> {
+        let mut bytes = vec![0u8; value_type.size()];
+        self.memory
+            .read_bytes(self.base_address + offset, &mut bytes)?;
+        Ok(Value::from_bytes(value_type, &bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// Two pages with a few u32 values, the second page is read-only.
+    fn process() -> MockProcess {
+        let mut process = MockProcess::new(1);
+        let mut data = vec![0u8; 0x1000];
+        for (index, value) in [5u32, 7, 5, 9].into_iter().enumerate() {
+            data[index * 0x10..index * 0x10 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        process
+            .add_region(0x10000, data, Protection::READ_WRITE)
+            .unwrap();
+        process
+            .add_region(0x20000, 5u32.to_le_bytes().repeat(0x400), Protection::READ)
+            .unwrap();
+        process
+    }
+
+    fn addresses<M: MemoryAccess>(scanner: &Scanner<M>) -> Vec<usize> {
+        scanner
+            .results(usize::MAX)
+            .unwrap()
+            .into_iter()
+            .map(|found| found.address)
+            .collect()
+    }
+
+    #[test]
+    fn first_scan_exact() {
+        let process = process();
+        let mut scanner = process.scanner(ValueType::U32);
+        assert_eq!(
+            scanner.first_scan(FirstScan::Exact(Value::U32(5))).unwrap(),
+            2
+        );
+        assert_eq!(addresses(&scanner), [0x10000, 0x10020]);
+        assert_eq!(scanner.results(1).unwrap().len(), 1);
+
+        let mut scanner = process.scanner(ValueType::U32).with_read_only(true);
+        assert_eq!(
+            scanner.first_scan(FirstScan::Exact(Value::U32(5))).unwrap(),
+            0x402
+        );
+        assert!(scanner.first_scan(FirstScan::Exact(Value::U8(5))).is_err());
+    }
+
+    #[test]
+    fn next_scan_narrows() {
+        let process = process();
+        let mut scanner = process.scanner(ValueType::U32);
+        assert!(scanner.next_scan(NextScan::Changed).is_err());
+        assert_eq!(scanner.first_scan(FirstScan::Unknown).unwrap(), 0x400);
+
+        process.memory.write(0x10000, 6u32).unwrap();
+        process.memory.write(0x10010, 3u32).unwrap();
+        assert_eq!(scanner.next_scan(NextScan::Changed).unwrap(), 2);
+        assert_eq!(scanner.next_scan(NextScan::Unchanged).unwrap(), 2);
+
+        process.memory.write(0x10000, 8u32).unwrap();
+        assert_eq!(scanner.next_scan(NextScan::Increased).unwrap(), 1);
+        assert_eq!(
+            scanner.results(10).unwrap(),
+            [ScanMatch {
+                address: 0x10000,
+                value: Value::U32(8)
+            }]
+        );
+    }
+
+    #[test]
+    fn unaligned_byte_scan() {
+        let process = process();
+        let mut scanner = process.scanner(ValueType::Bytes(2));
+        scanner
+            .first_scan(FirstScan::Exact(Value::Bytes(vec![0, 7])))
+            .unwrap();
+        assert_eq!(addresses(&scanner), [0x1000F]);
+    }
+
+    #[test]
+    fn results_keep_their_alignment() {
+        let process = process();
+        let mut scanner = process.scanner(ValueType::U32);
+        scanner.first_scan(FirstScan::Exact(Value::U32(9))).unwrap();
+        let mut scanner = scanner.with_alignment(1);
+        assert_eq!(scanner.next_scan(NextScan::Unchanged).unwrap(), 1);
+        assert_eq!(addresses(&scanner), [0x10030]);
+    }
+
+    #[test]
+    fn spilled_results() {
+        let process = process();
+        let mut scanner = process
+            .scanner(ValueType::U32)
+            .with_read_only(true)
+            .with_memory_limit(0);
+        assert_eq!(scanner.first_scan(FirstScan::Unknown).unwrap(), 0x800);
+        assert!(scanner.is_spilled());
+        let path = scanner
+            .results
+            .as_ref()
+            .unwrap()
+            .spill
+            .as_ref()
+            .unwrap()
+            .path
+            .clone();
+        assert!(path.exists());
+
+        process.memory.write(0x10010, 5u32).unwrap();
+        assert_eq!(
+            scanner.next_scan(NextScan::Exact(Value::U32(5))).unwrap(),
+            0x403
+        );
+        assert!(
+            !path.exists(),
+            "the spill file of replaced results is deleted"
+        );
+        assert_eq!(addresses(&scanner)[..3], [0x10000, 0x10010, 0x10020]);
+
+        scanner.reset();
+        assert_eq!(scanner.count(), 0);
+    }
+
+    /// Delegates to [`MockMemory`] but fails to list regions on demand.
+    struct FailingMemory {
+        inner: MockMemory,
+        fail: Cell<bool>,
+    }
+
+    impl MemoryAccess for FailingMemory {
+        fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+            self.inner.read_bytes(address, buffer)
+        }
+
+        fn write_bytes(&self, address: usize, data: &[u8]) -> Result<()> {
+            self.inner.write_bytes(address, data)
+        }
+
+        fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+            self.inner.query_region(address)
+        }
+
+        fn protect(
+            &self,
+            address: usize,
+            size: usize,
+            protection: Protection,
+        ) -> Result<Protection> {
+            self.inner.protect(address, size, protection)
+        }
+
+        fn regions(&self) -> Result<Vec<MemoryRegion>> {
+            if self.fail.get() {
+                return Err(anyhow!("regions are unavailable"));
+            }
+            self.inner.regions()
+        }
+    }
+
+    #[test]
+    fn failed_next_scan_keeps_results() {
+        let memory = FailingMemory {
+            inner: process().memory,
+            fail: Cell::new(false),
+        };
+        let mut scanner = Scanner::new(&memory, ValueType::U32);
+        scanner.first_scan(FirstScan::Exact(Value::U32(5))).unwrap();
+        memory.fail.set(true);
+        assert!(scanner.next_scan(NextScan::Unchanged).is_err());
+        assert_eq!(scanner.count(), 2);
+        memory.fail.set(false);
+        assert_eq!(scanner.next_scan(NextScan::Unchanged).unwrap(), 2);
+    }
+
+    #[test]
+    fn float_compare() {
+        assert!(FloatCompare::Rounded(1).matches(1.26, 1.34));
+        assert!(!FloatCompare::Truncated(1).matches(1.26, 1.34));
+        assert!(FloatCompare::Epsilon(0.1).matches(1.0, 1.05));
+        assert!(FloatCompare::Rounded(u32::MAX).matches(0.1, 0.1 + 1e-17));
+        assert!(!FloatCompare::Rounded(u32::MAX).matches(0.1, 0.2));
+        assert!(
+            !FloatCompare::Truncated(10).matches(1e300, f64::MAX),
+            "values too large to scale compare exactly"
+        );
+    }
+
+    #[test]
+    fn float_scan_skips_noise() {
+        let mut process = MockProcess::new(1);
+        let values = [1.5f32, f32::NAN, 1.52, f32::from_bits(1), 2.0];
+        let data = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        process
+            .add_region(0x10000, data, Protection::READ_WRITE)
+            .unwrap();
+        let mut scanner = process
+            .scanner(ValueType::F32)
+            .with_float_compare(FloatCompare::Rounded(1));
+        assert_eq!(scanner.first_scan(FirstScan::Unknown).unwrap(), 3);
+        assert_eq!(
+            scanner.next_scan(NextScan::Exact(Value::F32(1.5))).unwrap(),
+            2
+        );
+        assert_eq!(addresses(&scanner), [0x10000, 0x10008]);
+    }
+
+    #[test]
+    fn module_read_value() {
+        let mut process = MockProcess::new(1);
+        let module = process
+            .add_module(
+                "game.exe",
+                0x40_0000,
+                7u16.to_le_bytes().repeat(0x800),
+                Protection::READ,
+            )
+            .unwrap();
+        assert_eq!(module.read_value(2, ValueType::U16).unwrap(), Value::U16(7));
+        assert_eq!(
+            process.read_value(0x40_0004, ValueType::I16).unwrap(),
+            Value::I16(7)
+        );
+        assert!(module.read_value(0x1000, ValueType::U16).is_err());
+    }
+}
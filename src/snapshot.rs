@@ -0,0 +1,519 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::*;
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"CHEATSNP";
+const SNAPSHOT_VERSION: u32 = 1;
+const SNAPSHOT_PAGE_SIZE: usize = 0x1000;
+
+const CHUNK_ZERO: u8 = 0;
+const CHUNK_DATA: u8 = 1;
+const CHUNK_MISSING: u8 = 2;
+
+// File layout, all integers little endian:
+//
+// magic [u8; 8], version u32, page size u32, pointer width u32, process id u32
+// module count u32, per module: name length u32, name bytes, base address u64, size u64
+// region count u64, per region: base address u64, size u64, protection u8,
+//     per page: tag u8 followed by the page bytes if the tag is CHUNK_DATA
+
+fn write_u32(writer: &mut impl Write, value: u32) -> Result<()> {
+    Ok(writer.write_all(&value.to_le_bytes())?)
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> Result<()> {
+    Ok(writer.write_all(&value.to_le_bytes())?)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+/// Fails unless `needed` more bytes are left in a file of `file_length` bytes, lengths read from the file are
+/// checked before anything is allocated for them.
+fn check_remaining(
+    reader: &mut impl Seek,
+    file_length: u64,
+    needed: u64,
+    what: &str,
+) -> Result<()> {
+    let remaining = file_length.saturating_sub(reader.stream_position()?);
+    if needed > remaining {
+        return Err(anyhow!(
+            "snapshot is truncated or corrupt, {what} needs {needed} bytes but only {remaining} are left"
+        ));
+    }
+    Ok(())
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Writes every readable region and the module list into a snapshot file at `path`.
+    ///
+    /// Zero pages are stored as a single tag byte, pages that fail to read are marked as missing.
+    pub fn snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_snapshot(path.as_ref(), None)
+    }
+
+    /// Like [`Process::snapshot`] but only stores the memory and entries of the listed modules.
+    pub fn snapshot_modules(&self, path: impl AsRef<Path>, module_names: &[&str]) -> Result<()> {
+        self.write_snapshot(path.as_ref(), Some(module_names))
+    }
+
+    fn write_snapshot(&self, path: &Path, module_names: Option<&[&str]>) -> Result<()> {
+        let modules = self
+            .modules
+            .iter()
+            .filter(|module| module_names.is_none_or(|names| names.contains(&module.name.as_str())))
+            .collect::<Vec<_>>();
+
+        let mut regions = self
+            .memory
+            .regions()?
+            .into_iter()
+            .filter(MemoryRegion::is_readable)
+            .collect::<Vec<_>>();
+        if module_names.is_some() {
+            regions = regions
+                .iter()
+                .flat_map(|region| {
+                    modules.iter().filter_map(|module| {
+                        let start = region.base_address.max(module.base_address);
+                        let end = region.end_address().min(module.base_address + module.size);
+                        (start < end).then(|| MemoryRegion {
+                            base_address: start,
+                            size: end - start,
+                            ..region.clone()
+                        })
+                    })
+                })
+                .collect();
+        }
+
+        let file = File::create(path)
+            .map_err(|error| anyhow!("failed to create snapshot {}: {error}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        write_u32(&mut writer, SNAPSHOT_VERSION)?;
+        write_u32(&mut writer, SNAPSHOT_PAGE_SIZE as u32)?;
+        write_u32(&mut writer, self.memory.pointer_width() as u32)?;
+        write_u32(&mut writer, self.id)?;
+
+        write_u32(&mut writer, modules.len() as u32)?;
+        for module in modules.iter() {
+            write_u32(&mut writer, module.name.len() as u32)?;
+            writer.write_all(module.name.as_bytes())?;
+            write_u64(&mut writer, module.base_address as u64)?;
+            write_u64(&mut writer, module.size as u64)?;
+        }
+
+        write_u64(&mut writer, regions.len() as u64)?;
+        let mut page = vec![0u8; SNAPSHOT_PAGE_SIZE];
+        for region in regions.iter() {
+            write_u64(&mut writer, region.base_address as u64)?;
+            write_u64(&mut writer, region.size as u64)?;
+            writer.write_all(&[region.protection.bits()])?;
+
+            for offset in (0..region.size).step_by(SNAPSHOT_PAGE_SIZE) {
+                let page = &mut page[..SNAPSHOT_PAGE_SIZE.min(region.size - offset)];
+                if self
+                    .memory
+                    .read_bytes(region.base_address + offset, page)
+                    .is_err()
+                {
+                    writer.write_all(&[CHUNK_MISSING])?;
+                } else if page.iter().all(|&byte| byte == 0) {
+                    writer.write_all(&[CHUNK_ZERO])?;
+                } else {
+                    writer.write_all(&[CHUNK_DATA])?;
+                    writer.write_all(page)?;
+                }
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SnapshotChunk {
+    Zero,
+    Data(u64),
+    Missing,
+}
+
+#[derive(Debug)]
+struct SnapshotRegion {
+    base_address: usize,
+    size: usize,
+    protection: Protection,
+    chunks: Vec<SnapshotChunk>,
+}
+
+impl SnapshotRegion {
+    #[inline]
+    fn end_address(&self) -> usize {
+        self.base_address + self.size
+    }
+}
+
+#[derive(Debug)]
+struct SnapshotFile {
+    file: Mutex<File>,
+    page_size: usize,
+    pointer_width: usize,
+    regions: Vec<SnapshotRegion>,
+}
+
+/// Read-only backend over a snapshot written by [`Process::snapshot`].
+///
+/// Only the region table is kept in memory, page contents are read from the file on demand.
+#[derive(Clone, Debug)]
+pub struct SnapshotMemory {
+    snapshot: Arc<SnapshotFile>,
+}
+
+impl MemoryAccess for SnapshotMemory {
+    fn read_bytes(&self, address: usize, buffer: &mut [u8]) -> Result<()> {
+        let page_size = self.snapshot.page_size;
+        let mut file = self.snapshot.file.lock().unwrap();
+
+        let mut cursor = address;
+        let end_address = address
+            .checked_add(buffer.len())
+            .ok_or_else(|| anyhow!("read at {:#0x} overflows", address))?;
+        while cursor < end_address {
+            let Some(region) = self
+                .snapshot
+                .regions
+                .iter()
+                .find(|region| region.base_address <= cursor && cursor < region.end_address())
+            else {
+                return Err(anyhow!("snapshot memory at {:#0x} is not mapped", cursor));
+            };
+
+            let region_offset = cursor - region.base_address;
+            let page_offset = region_offset % page_size;
+            let length = (page_size - page_offset)
+                .min(end_address - cursor)
+                .min(region.end_address() - cursor);
+            let destination = &mut buffer[cursor - address..cursor - address + length];
+
+            match region.chunks[region_offset / page_size] {
+                SnapshotChunk::Zero => destination.fill(0),
+                SnapshotChunk::Data(file_offset) => {
+                    file.seek(SeekFrom::Start(file_offset + page_offset as u64))?;
+                    file.read_exact(destination)?;
+                }
+                SnapshotChunk::Missing => {
+                    return Err(anyhow!(
+                        "snapshot memory at {:#0x} could not be read when the snapshot was taken",
+                        cursor
+                    ))
+                }
+            }
+            cursor += length;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, address: usize, _data: &[u8]) -> Result<()> {
+        Err(anyhow!(
+            "cannot write to {:#0x}, snapshots are read-only",
+            address
+        ))
+    }
+
+    fn query_region(&self, address: usize) -> Result<MemoryRegion> {
+        let regions = &self.snapshot.regions;
+        let mut gap_start = 0;
+        for region in regions.iter() {
+            if address < region.base_address {
+                break;
+            }
+            if address < region.end_address() {
+                return Ok(MemoryRegion {
+                    base_address: region.base_address,
+                    size: region.size,
+                    protection: region.protection,
+                    ..Default::default()
+                });
+            }
+            gap_start = region.end_address();
+        }
+        let gap_end = regions
+            .iter()
+            .map(|region| region.base_address)
+            .find(|&base_address| base_address > address)
+            .unwrap_or(usize::MAX);
+        Ok(MemoryRegion {
+            base_address: gap_start,
+            size: gap_end - gap_start,
+            state: RegionState::Free,
+            ..Default::default()
+        })
+    }
+
+    fn protect(&self, address: usize, _size: usize, _protection: Protection) -> Result<Protection> {
+        Err(anyhow!(
+            "cannot change the protection of {:#0x}, snapshots are read-only",
+            address
+        ))
+    }
+
+    /// Pointer width of the process the snapshot was taken from.
+    fn pointer_width(&self) -> usize {
+        self.snapshot.pointer_width
+    }
+}
+
+/// A [`Process`] reopened from a snapshot file.
+pub type SnapshotProcess = Process<SnapshotMemory>;
+
+impl Process<SnapshotMemory> {
+    pub fn open_snapshot(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|error| anyhow!("failed to open snapshot {}: {error}", path.display()))?;
+        let file_length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(anyhow!("{} is not a snapshot file", path.display()));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(anyhow!(
+                "unsupported snapshot version {version}, expected {SNAPSHOT_VERSION}"
+            ));
+        }
+        let page_size = read_u32(&mut reader)? as usize;
+        if page_size == 0 {
+            return Err(anyhow!(
+                "snapshot {} has a page size of zero",
+                path.display()
+            ));
+        }
+        let pointer_width = read_u32(&mut reader)? as usize;
+        if pointer_width != 4 && pointer_width != 8 {
+            return Err(anyhow!(
+                "snapshot {} has an invalid pointer width of {pointer_width}",
+                path.display()
+            ));
+        }
+        let id = read_u32(&mut reader)?;
+
+        let mut modules = vec![];
+        for _ in 0..read_u32(&mut reader)? {
+            let name_length = read_u32(&mut reader)?;
+            check_remaining(
+                &mut reader,
+                file_length,
+                name_length.into(),
+                "a module name",
+            )?;
+            let mut name = vec![0u8; name_length as usize];
+            reader.read_exact(&mut name)?;
+            let base_address = read_u64(&mut reader)? as usize;
+            let size = read_u64(&mut reader)? as usize;
+            modules.push((
+                String::from_utf8_lossy(&name).into_owned(),
+                base_address,
+                size,
+            ));
+        }
+
+        let mut regions = vec![];
+        for _ in 0..read_u64(&mut reader)? {
+            let base_address = read_u64(&mut reader)? as usize;
+            let size = read_u64(&mut reader)? as usize;
+            let protection = Protection::from_bits(read_u8(&mut reader)?);
+            if base_address.checked_add(size).is_none() {
+                return Err(anyhow!(
+                    "snapshot region at {:#0x} with size {:#0x} overflows",
+                    base_address,
+                    size
+                ));
+            }
+
+            // every page takes at least its tag byte
+            let page_count = size.div_ceil(page_size);
+            check_remaining(&mut reader, file_length, page_count as u64, "a region")?;
+            let mut chunks = Vec::with_capacity(page_count);
+            for offset in (0..size).step_by(page_size) {
+                let chunk = match read_u8(&mut reader)? {
+                    CHUNK_ZERO => SnapshotChunk::Zero,
+                    CHUNK_DATA => {
+                        let file_offset = reader.stream_position()?;
+                        let length = page_size.min(size - offset) as i64;
+                        check_remaining(&mut reader, file_length, length as u64, "a page")?;
+                        reader.seek_relative(length)?;
+                        SnapshotChunk::Data(file_offset)
+                    }
+                    CHUNK_MISSING => SnapshotChunk::Missing,
+                    tag => return Err(anyhow!("invalid page tag {tag} in snapshot")),
+                };
+                chunks.push(chunk);
+            }
+
+            regions.push(SnapshotRegion {
+                base_address,
+                size,
+                protection,
+                chunks,
+            });
+        }
+        regions.sort_by_key(|region| region.base_address);
+
+        let memory = SnapshotMemory {
+            snapshot: Arc::new(SnapshotFile {
+                file: Mutex::new(reader.into_inner()),
+                page_size,
+                pointer_width,
+                regions,
+            }),
+        };
+        Ok(Self {
+            id,
+            modules: modules
+                .into_iter()
+                .map(|(name, base_address, size)| Module {
+                    name,
+                    handle: 0,
+                    size,
+                    base_address,
+                    memory: memory.clone(),
+                })
+                .collect(),
+            memory,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("cheatlib-test-{}-{name}.snp", std::process::id()))
+    }
+
+    fn process() -> MockProcess {
+        let mut process = MockProcess::new(42);
+        let mut module = vec![0u8; 0x2000];
+        module[0x1234] = 0xAB;
+        process
+            .add_module("game.exe", 0x40_0000, module, Protection::READ_EXECUTE)
+            .unwrap();
+        let mut heap = vec![0u8; 0x1800];
+        heap[0x1700..0x1708].copy_from_slice(&0x40_1234u64.to_le_bytes());
+        process
+            .add_region(0x100_0000, heap, Protection::READ_WRITE)
+            .unwrap();
+        process
+            .add_region(0x200_0000, vec![1u8; 0x1000], Protection::NONE)
+            .unwrap();
+        process
+    }
+
+    #[test]
+    fn round_trips_memory_and_modules() {
+        let path = temp_path("round-trip");
+        process().snapshot(&path).unwrap();
+        let snapshot = SnapshotProcess::open_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot.id, 42);
+        assert_eq!(snapshot.modules.len(), 1);
+        assert_eq!(snapshot.modules[0].base_address, 0x40_0000);
+        assert_eq!(snapshot.modules[0].read::<u8>(0x1234).unwrap(), 0xAB);
+        assert_eq!(
+            snapshot.memory.read::<u64>(0x100_16FC).unwrap(),
+            0x40_1234 << 32
+        );
+        assert_eq!(
+            snapshot
+                .resolve(&PointerChain::from_address(0x100_1700, &[0]))
+                .unwrap(),
+            0x40_1234
+        );
+        assert!(
+            snapshot.memory.read::<u8>(0x200_0000).is_err(),
+            "unreadable regions are left out"
+        );
+        assert!(snapshot.memory.write(0x100_0000, 1u8).is_err());
+        assert_eq!(snapshot.memory.regions().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn snapshots_selected_modules() {
+        let path = temp_path("modules");
+        process().snapshot_modules(&path, &["game.exe"]).unwrap();
+        let snapshot = SnapshotProcess::open_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot.memory.regions().unwrap().len(), 1);
+        assert!(snapshot.memory.read::<u8>(0x100_0000).is_err());
+    }
+
+    #[test]
+    fn rejects_corrupt_lengths() {
+        let path = temp_path("corrupt");
+        process().snapshot(&path).unwrap();
+        let original = std::fs::read(&path).unwrap();
+
+        // name length of the first module
+        let mut corrupt = original.clone();
+        corrupt[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupt).unwrap();
+        assert!(SnapshotProcess::open_snapshot(&path).is_err());
+
+        // size of the first region, after the module entry
+        let region = 32 + "game.exe".len() + 16 + 8;
+        let mut corrupt = original.clone();
+        corrupt[region + 8..region + 16].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        std::fs::write(&path, &corrupt).unwrap();
+        assert!(SnapshotProcess::open_snapshot(&path).is_err());
+
+        std::fs::write(&path, &original[..original.len() / 2]).unwrap();
+        assert!(SnapshotProcess::open_snapshot(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_truncated_pages() {
+        let path = temp_path("truncated");
+        process().snapshot(&path).unwrap();
+        let original = std::fs::read(&path).unwrap();
+
+        // cuts into the data of the last page
+        std::fs::write(&path, &original[..original.len() - 4]).unwrap();
+        let error = SnapshotProcess::open_snapshot(&path).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.to_string().contains("a page needs"), "{error}");
+    }
+}
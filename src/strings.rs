@@ -0,0 +1,376 @@
+use crate::{memory::next_page, *};
+
+/// C strings are read in chunks of at most this many bytes, never crossing a page boundary.
+const STRING_CHUNK_SIZE: usize = 256;
+
+/// String read from target memory.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RemoteString {
+    pub value: String,
+    /// The limit was hit before a terminator, or a length prefix exceeded it, so `value` is cut short.
+    pub truncated: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StringEncoding {
+    Utf8,
+    /// UTF-16LE, lengths and limits count code units rather than bytes.
+    Utf16,
+}
+
+impl StringEncoding {
+    #[inline]
+    fn unit_size(self) -> usize {
+        match self {
+            Self::Utf8 => 1,
+            Self::Utf16 => 2,
+        }
+    }
+
+    fn decode(self, bytes: &[u8], address: usize) -> Result<String> {
+        match self {
+            Self::Utf8 => String::from_utf8(bytes.to_vec()).map_err(|error| {
+                anyhow!(
+                    "string at {:#0x} is not valid UTF-8 at byte {}",
+                    address,
+                    error.utf8_error().valid_up_to()
+                )
+            }),
+            Self::Utf16 => {
+                let units = bytes
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<_>>();
+                String::from_utf16(&units)
+                    .map_err(|_| anyhow!("string at {:#0x} is not valid UTF-16", address))
+            }
+        }
+    }
+
+    /// Like `decode`, but drops a character that was cut in half by the limit.
+    fn decode_truncated(self, bytes: &[u8], address: usize) -> Result<String> {
+        let length = match self {
+            Self::Utf8 => match std::str::from_utf8(bytes) {
+                Err(error) if error.error_len().is_none() => error.valid_up_to(),
+                _ => bytes.len(),
+            },
+            Self::Utf16 => match bytes.rchunks_exact(2).next() {
+                Some(unit)
+                    if (0xD800..0xDC00).contains(&u16::from_le_bytes([unit[0], unit[1]])) =>
+                {
+                    bytes.len() - 2
+                }
+                _ => bytes.len(),
+            },
+        };
+        self.decode(&bytes[..length], address)
+    }
+}
+
+/// Width of the length field in front of a length-prefixed string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthPrefix {
+    U8,
+    U16,
+    U32,
+}
+
+/// Reads a null terminated string of at most `max_units` code units.
+pub fn read_terminated_string<M: MemoryAccess + ?Sized>(
+    memory: &M,
+    address: usize,
+    encoding: StringEncoding,
+    max_units: usize,
+) -> Result<RemoteString> {
+    let unit_size = encoding.unit_size();
+    let max_bytes = max_units
+        .checked_mul(unit_size)
+        .ok_or_else(|| anyhow!("string limit {max_units} is too large"))?;
+
+    let mut bytes = vec![];
+    let mut chunk = [0u8; STRING_CHUNK_SIZE];
+    while bytes.len() < max_bytes {
+        let current = address
+            .checked_add(bytes.len())
+            .ok_or_else(|| anyhow!("string at {:#0x} overflows", address))?;
+        let length = STRING_CHUNK_SIZE
+            .min(next_page(current) - current)
+            .min(max_bytes - bytes.len());
+        let chunk = &mut chunk[..length];
+        memory.read_bytes(current, chunk).map_err(|error| {
+            anyhow!(
+                "failed to read string at {:#0x} after {} bytes: {error}",
+                address,
+                bytes.len()
+            )
+        })?;
+        // only the new chunk is searched, starting at the unit it completes
+        let start = bytes.len() - bytes.len() % unit_size;
+        bytes.extend_from_slice(chunk);
+
+        let terminator = bytes[start..]
+            .chunks_exact(unit_size)
+            .position(|unit| unit.iter().all(|&byte| byte == 0));
+        if let Some(terminator) = terminator {
+            bytes.truncate(start + terminator * unit_size);
+            return Ok(RemoteString {
+                value: encoding.decode(&bytes, address)?,
+                truncated: false,
+            });
+        }
+    }
+
+    Ok(RemoteString {
+        value: encoding.decode_truncated(&bytes, address)?,
+        truncated: true,
+    })
+}
+
+/// Reads a character array of `units` code units, the string ends at the first null terminator inside it.
+///
+/// `truncated` is set when the array holds no terminator, since the string may continue past it.
+pub fn read_fixed_string<M: MemoryAccess + ?Sized>(
+    memory: &M,
+    address: usize,
+    encoding: StringEncoding,
+    units: usize,
+) -> Result<RemoteString> {
+    let unit_size = encoding.unit_size();
+    let length = units
+        .checked_mul(unit_size)
+        .ok_or_else(|| anyhow!("string of {units} units is too large"))?;
+    let mut bytes = vec![0u8; length];
+    memory.read_bytes(address, &mut bytes)?;
+
+    let terminator = bytes
+        .chunks_exact(unit_size)
+        .position(|unit| unit.iter().all(|&byte| byte == 0));
+    if let Some(terminator) = terminator {
+        bytes.truncate(terminator * unit_size);
+    }
+    let value = match terminator {
+        Some(_) => encoding.decode(&bytes, address)?,
+        None => encoding.decode_truncated(&bytes, address)?,
+    };
+    Ok(RemoteString {
+        value,
+        truncated: terminator.is_none(),
+    })
+}
+
+/// Reads a string preceded by its length in code units, reading at most `max_units` of them.
+pub fn read_length_prefixed_string<M: MemoryAccess + ?Sized>(
+    memory: &M,
+    address: usize,
+    prefix: LengthPrefix,
+    encoding: StringEncoding,
+    max_units: usize,
+) -> Result<RemoteString> {
+    let (length, prefix_size) = match prefix {
+        LengthPrefix::U8 => {
+            let mut length = [0u8; 1];
+            memory.read_bytes(address, &mut length)?;
+            (length[0] as usize, 1)
+        }
+        LengthPrefix::U16 => {
+            let mut length = [0u8; 2];
+            memory.read_bytes(address, &mut length)?;
+            (u16::from_le_bytes(length) as usize, 2)
+        }
+        LengthPrefix::U32 => {
+            let mut length = [0u8; 4];
+            memory.read_bytes(address, &mut length)?;
+            (u32::from_le_bytes(length) as usize, 4)
+        }
+    };
+
+    let units = length.min(max_units);
+    let size = units
+        .checked_mul(encoding.unit_size())
+        .ok_or_else(|| anyhow!("string of {units} units is too large"))?;
+    let mut bytes = vec![0u8; size];
+    memory.read_bytes(address + prefix_size, &mut bytes)?;
+    let value = if length > max_units {
+        encoding.decode_truncated(&bytes, address)?
+    } else {
+        encoding.decode(&bytes, address)?
+    };
+    Ok(RemoteString {
+        value,
+        truncated: length > max_units,
+    })
+}
+
+impl<M: MemoryAccess> Process<M> {
+    /// Reads a null terminated UTF-8 string of at most `max_length` bytes.
+    pub fn read_c_string(&self, address: usize, max_length: usize) -> Result<RemoteString> {
+        read_terminated_string(&self.memory, address, StringEncoding::Utf8, max_length)
+    }
+
+    /// Reads a null terminated UTF-16LE string of at most `max_length` code units.
+    pub fn read_wide_string(&self, address: usize, max_length: usize) -> Result<RemoteString> {
+        read_terminated_string(&self.memory, address, StringEncoding::Utf16, max_length)
+    }
+
+    pub fn read_fixed_string(
+        &self,
+        address: usize,
+        encoding: StringEncoding,
+        length: usize,
+    ) -> Result<RemoteString> {
+        read_fixed_string(&self.memory, address, encoding, length)
+    }
+
+    pub fn read_length_prefixed_string(
+        &self,
+        address: usize,
+        prefix: LengthPrefix,
+        encoding: StringEncoding,
+        max_length: usize,
+    ) -> Result<RemoteString> {
+        read_length_prefixed_string(&self.memory, address, prefix, encoding, max_length)
+    }
+}
+
+impl<M: MemoryAccess> Module<M> {
+    /// Reads a null terminated UTF-8 string `offset` bytes after the module base address.
+    pub fn read_c_string(&self, offset: usize, max_length: usize) -> Result<RemoteString> {
+        read_terminated_string(
+            &self.memory,
+            self.base_address + offset,
+            StringEncoding::Utf8,
+            max_length,
+        )
+    }
+
+    /// Reads a null terminated UTF-16LE string `offset` bytes after the module base address.
+    pub fn read_wide_string(&self, offset: usize, max_length: usize) -> Result<RemoteString> {
+        read_terminated_string(
+            &self.memory,
+            self.base_address + offset,
+            StringEncoding::Utf16,
+            max_length,
+        )
+    }
+
+    pub fn read_fixed_string(
+        &self,
+        offset: usize,
+        encoding: StringEncoding,
+        length: usize,
+    ) -> Result<RemoteString> {
+        read_fixed_string(&self.memory, self.base_address + offset, encoding, length)
+    }
+
+    pub fn read_length_prefixed_string(
+        &self,
+        offset: usize,
+        prefix: LengthPrefix,
+        encoding: StringEncoding,
+        max_length: usize,
+    ) -> Result<RemoteString> {
+        read_length_prefixed_string(
+            &self.memory,
+            self.base_address + offset,
+            prefix,
+            encoding,
+            max_length,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two readable pages at 0x1000 followed by an unmapped one.
+    fn memory_with(address: usize, data: &[u8]) -> MockMemory {
+        let memory = MockMemory::new();
+        memory
+            .add_region(0x1000, vec![b'x'; 0x2000], Protection::READ_WRITE)
+            .unwrap();
+        memory.write_bytes(address, data).unwrap();
+        memory
+    }
+
+    fn wide(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn terminator_across_chunks() {
+        let text = "a".repeat(STRING_CHUNK_SIZE - 1);
+        let memory = memory_with(0x1000, format!("{text}b\0").as_bytes());
+        let string = read_terminated_string(&memory, 0x1000, StringEncoding::Utf8, 0x1000).unwrap();
+        assert_eq!(string.value, format!("{text}b"));
+        assert!(!string.truncated);
+
+        // an odd address makes the page boundary split a UTF-16 unit
+        let text = "é".repeat(0x10);
+        let mut data = wide(&text);
+        data.extend_from_slice(&[0, 0]);
+        let address = 0x2000 - 0x21;
+        let memory = memory_with(address, &data);
+        let string =
+            read_terminated_string(&memory, address, StringEncoding::Utf16, 0x100).unwrap();
+        assert_eq!(string.value, text);
+
+        let mut data = wide("ab");
+        data.extend_from_slice(&[0, 0x4E, 0, 0]);
+        let memory = memory_with(0x1000, &data);
+        let string = read_terminated_string(&memory, 0x1000, StringEncoding::Utf16, 0x100).unwrap();
+        assert_eq!(
+            string.value, "ab一",
+            "a zero byte inside a unit is no terminator"
+        );
+    }
+
+    #[test]
+    fn truncates_at_the_limit() {
+        let memory = memory_with(0x1000, "héllo\0".as_bytes());
+        let string = read_terminated_string(&memory, 0x1000, StringEncoding::Utf8, 2).unwrap();
+        assert_eq!(string.value, "h", "the cut character is dropped");
+        assert!(string.truncated);
+        let string = read_terminated_string(&memory, 0x1000, StringEncoding::Utf8, 7).unwrap();
+        assert_eq!(string.value, "héllo");
+        assert!(!string.truncated);
+
+        let string = read_fixed_string(&memory, 0x1000, StringEncoding::Utf8, 3).unwrap();
+        assert_eq!((string.value.as_str(), string.truncated), ("hé", true));
+        assert!(read_fixed_string(&memory, 0x1000, StringEncoding::Utf16, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn utf16_surrogates() {
+        let memory = memory_with(0x1000, &wide("a😀b\0"));
+        let string = read_terminated_string(&memory, 0x1000, StringEncoding::Utf16, 2).unwrap();
+        assert_eq!(string.value, "a", "half a surrogate pair is dropped");
+        assert!(string.truncated);
+        let string = read_terminated_string(&memory, 0x1000, StringEncoding::Utf16, 3).unwrap();
+        assert_eq!(string.value, "a😀");
+
+        let memory = memory_with(0x1000, &[0x00, 0xD8, b'a', 0, 0, 0]);
+        assert!(read_terminated_string(&memory, 0x1000, StringEncoding::Utf16, 0x10).is_err());
+    }
+
+    #[test]
+    fn stops_at_unmapped_pages() {
+        let memory = memory_with(0x2FFC, b"abc\0");
+        let string = read_terminated_string(&memory, 0x2FFC, StringEncoding::Utf8, 0x100).unwrap();
+        assert_eq!(string.value, "abc");
+
+        let memory = memory_with(0x2FFC, b"abcd");
+        let error =
+            read_terminated_string(&memory, 0x2FFC, StringEncoding::Utf8, 0x100).unwrap_err();
+        assert!(error.to_string().contains("after 4 bytes"), "{error}");
+    }
+
+    #[test]
+    fn length_prefixed() {
+        let memory = memory_with(0x1000, b"\x05hello");
+        let string =
+            read_length_prefixed_string(&memory, 0x1000, LengthPrefix::U8, StringEncoding::Utf8, 3)
+                .unwrap();
+        assert_eq!((string.value.as_str(), string.truncated), ("hel", true));
+    }
+}
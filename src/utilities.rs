@@ -12,9 +12,10 @@ pub fn is_page_readable(memory_info: &MEMORY_BASIC_INFORMATION) -> bool {
         || memory_info.Protect == PAGE_NOACCESS)
 }
 
+/// Keep the returned string alive for as long as the pointer to it is in use. Fails if `text` contains a NUL.
 #[inline]
-pub fn make_lpcstr(text: &str) -> *const u8 {
-    format!("{}{}", text, "\0").as_ptr()
+pub fn make_lpcstr(text: &str) -> Result<CString> {
+    CString::new(text).map_err(|_| anyhow!("{text:?} contains a NUL byte"))
 }
 
 /// # Safety
@@ -15,9 +15,12 @@ use windows_sys::Win32::{
             },
         },
         LibraryLoader::GetModuleHandleA,
-        Memory::{VirtualAllocEx, VirtualProtect, VirtualQuery, MEM_COMMIT, MEM_RESERVE},
-        ProcessStatus::GetModuleInformation,
-        Threading::{CreateRemoteThread, CreateThread, GetCurrentProcess},
+        Memory::{
+            VirtualAllocEx, VirtualFreeEx, VirtualProtect, VirtualProtectEx, VirtualQuery,
+            VirtualQueryEx, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE,
+        },
+        ProcessStatus::{GetMappedFileNameW, GetModuleInformation},
+        Threading::{CreateRemoteThread, CreateThread, GetCurrentProcess, IsWow64Process},
     },
 };
 
@@ -79,10 +82,9 @@ pub fn get_process_entry_by_name(name: &str) -> Option<PROCESSENTRY32> {
     }
 }
 
-pub fn get_process_modules(process: (HANDLE, u32)) -> Vec<Module> {
+pub fn get_process_modules<M: Clone>(memory: &M, process_id: u32) -> Vec<Module<M>> {
     let mut modules = vec![];
 
-    let (process_handle, process_id) = process;
     let Some(snapshot) = create_toolhelp32_snapshot(TH32CS_SNAPMODULE, process_id) else {
         return modules;
     };
@@ -91,16 +93,17 @@ pub fn get_process_modules(process: (HANDLE, u32)) -> Vec<Module> {
     entry.dwSize = mem::size_of::<MODULEENTRY32>() as u32;
 
     if unsafe { Module32First(snapshot, &mut entry) } == FALSE {
+        close_handle(snapshot);
         return modules;
     }
 
     loop {
         modules.push(Module {
             name: unsafe { read_null_terminated_string(entry.szModule.as_ptr()) }.to_string(),
-            process_handle,
             handle: entry.hModule,
-            size: entry.dwSize as usize,
+            size: entry.modBaseSize as usize,
             base_address: entry.modBaseAddr as usize,
+            memory: memory.clone(),
         });
 
         if unsafe { Module32Next(snapshot, &mut entry) } == FALSE {
@@ -196,8 +199,8 @@ pub fn write_process_memory<T>(
 
 #[cfg(windows)]
 pub fn get_module_handle(name: &str) -> Result<HMODULE> {
-    let module_name = make_lpcstr(name);
-    let module_handle = unsafe { GetModuleHandleA(module_name) };
+    let module_name = make_lpcstr(name)?;
+    let module_handle = unsafe { GetModuleHandleA(module_name.as_ptr() as *const u8) };
     if module_handle <= 0 {
         let error_code = unsafe { GetLastError() };
         let error_message = format!(
@@ -274,20 +277,65 @@ pub unsafe fn get_module_data(module_info: MODULEINFO) -> Vec<u8> {
 
 #[cfg(windows)]
 pub fn virtual_alloc_ex(process_handle: HANDLE, size: usize) -> Result<*mut c_void> {
+    virtual_alloc_ex_at(process_handle, None, size, PAGE_EXECUTE_READWRITE)
+        .map(|address| address as *mut c_void)
+}
+
+/// Commits `size` bytes with `protect` page flags, at exactly `address` if given.
+#[cfg(windows)]
+pub fn virtual_alloc_ex_at(
+    process_handle: HANDLE,
+    address: Option<usize>,
+    size: usize,
+    protect: u32,
+) -> Result<usize> {
     let remote_memory = unsafe {
         VirtualAllocEx(
             process_handle,
-            ptr::null_mut(),
+            address.unwrap_or_default() as *const c_void,
             size,
             MEM_COMMIT | MEM_RESERVE,
-            PAGE_EXECUTE_READWRITE,
+            protect,
         )
     };
 
     if remote_memory.is_null() {
-        return Err(anyhow!("Failed to allocate memory in remote process"));
+        let error_code = unsafe { GetLastError() };
+        return Err(anyhow!(
+            "VirtualAllocEx failed for {} bytes at {:#0x}. Error code: {}. Description: {}",
+            size,
+            address.unwrap_or_default(),
+            error_code,
+            std::io::Error::from_raw_os_error(error_code as i32)
+        ));
+    }
+    // a preferred address is rounded down to the allocation granularity instead of failing
+    match address {
+        Some(address) if address != remote_memory as usize => {
+            let _ = virtual_free_ex(process_handle, remote_memory as usize);
+            Err(anyhow!(
+                "VirtualAllocEx placed {:#0x} at {:#0x} instead",
+                address,
+                remote_memory as usize
+            ))
+        }
+        _ => Ok(remote_memory as usize),
+    }
+}
+
+/// Releases a whole allocation made by [`virtual_alloc_ex_at`].
+#[cfg(windows)]
+pub fn virtual_free_ex(process_handle: HANDLE, address: usize) -> Result<()> {
+    if unsafe { VirtualFreeEx(process_handle, address as *mut c_void, 0, MEM_RELEASE) } == FALSE {
+        let error_code = unsafe { GetLastError() };
+        return Err(anyhow!(
+            "VirtualFreeEx failed for target: {:#0x}. Error code: {}. Description: {}",
+            address,
+            error_code,
+            std::io::Error::from_raw_os_error(error_code as i32)
+        ));
     }
-    Ok(remote_memory)
+    Ok(())
 }
 
 #[cfg(windows)]
@@ -335,3 +383,110 @@ pub fn virtual_query(target: *const ()) -> Result<MEMORY_BASIC_INFORMATION> {
     }
     Ok(memory_info)
 }
+
+#[cfg(windows)]
+pub fn virtual_protect_ex(
+    process_handle: HANDLE,
+    address: usize,
+    size: usize,
+    new_protect: u32,
+    old_protect: &mut u32,
+) -> Result<()> {
+    if unsafe {
+        VirtualProtectEx(
+            process_handle,
+            address as *const c_void,
+            size,
+            new_protect,
+            old_protect,
+        )
+    } == FALSE
+    {
+        let error_code = unsafe { GetLastError() };
+        let error_message = format!(
+            "VirtualProtectEx failed for target: {:#0x}. Error code: {}. Description: {}",
+            address,
+            error_code,
+            std::io::Error::from_raw_os_error(error_code as i32)
+        );
+        return Err(anyhow::anyhow!(error_message));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn virtual_query_ex(
+    process_handle: HANDLE,
+    address: usize,
+) -> Result<MEMORY_BASIC_INFORMATION> {
+    let mut memory_info: MEMORY_BASIC_INFORMATION =
+        unsafe { mem::zeroed::<MEMORY_BASIC_INFORMATION>() };
+    let result = unsafe {
+        VirtualQueryEx(
+            process_handle,
+            address as *const c_void,
+            &mut memory_info,
+            std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+        )
+    };
+    if result == 0 {
+        let error_code = unsafe { GetLastError() };
+        let error_message = format!(
+            "VirtualQueryEx failed for target: {:#0x}. Error code: {}. Description: {}",
+            address,
+            error_code,
+            std::io::Error::from_raw_os_error(error_code as i32)
+        );
+        return Err(anyhow::anyhow!(error_message));
+    }
+    Ok(memory_info)
+}
+
+#[cfg(windows)]
+pub fn get_mapped_file_name(process_handle: HANDLE, address: usize) -> Option<String> {
+    let mut file_name = [0u16; 1024];
+    let length = unsafe {
+        GetMappedFileNameW(
+            process_handle,
+            address as *const c_void,
+            file_name.as_mut_ptr(),
+            file_name.len() as u32,
+        )
+    };
+    if length == 0 {
+        return None;
+    }
+    Some(String::from_utf16_lossy(&file_name[..length as usize]))
+}
+
+/// Walks the address space with `VirtualQueryEx` and returns every region that is not free.
+/// Image and mapped regions carry the device path of their backing file.
+#[cfg(windows)]
+pub fn query_process_regions(process_handle: HANDLE) -> Result<Vec<MemoryRegion>> {
+    let mut regions = vec![];
+    let mut address = 0usize;
+    while let Ok(memory_info) = virtual_query_ex(process_handle, address) {
+        let mut region = MemoryRegion::from(&memory_info);
+        let next_address = region.base_address.checked_add(region.size);
+
+        if region.state != RegionState::Free {
+            if region.kind != RegionKind::Private {
+                region.path = get_mapped_file_name(process_handle, region.base_address);
+            }
+            regions.push(region);
+        }
+
+        match next_address {
+            Some(next_address) if next_address > address => address = next_address,
+            _ => break,
+        }
+    }
+    Ok(regions)
+}
+
+/// Returns whether a 32-bit process is running under WOW64 on a 64-bit system.
+#[cfg(windows)]
+pub fn is_wow64_process(process_handle: HANDLE) -> bool {
+    let mut is_wow64 = FALSE;
+    (unsafe { IsWow64Process(process_handle, &mut is_wow64) }) != FALSE && is_wow64 != FALSE
+}